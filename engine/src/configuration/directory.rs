@@ -1,72 +1,198 @@
-use crate::configuration::{Configuration, ConfigurationLoader};
-use std::collections::HashMap;
+use crate::configuration::{Configuration, ConfigurationError, ConfigurationLoader};
+use std::collections::{HashMap, HashSet};
 use std::fs::{read_dir, File};
 use std::io::Read;
 use std::path::Path;
 use std::time::SystemTime;
 use toml::Table;
 
-pub struct DirectoryConfigurationLoader<P: AsRef<Path>> {
+pub struct DirectoryConfigurationLoader<P: AsRef<Path> + Send> {
     base: P,
     watched: HashMap<String, SystemTime>,
 }
 
-impl<P: AsRef<Path>> DirectoryConfigurationLoader<P> {
+impl<P: AsRef<Path> + Send> DirectoryConfigurationLoader<P> {
     pub fn new(base: P) -> Self {
         Self {
             base,
             watched: Default::default(),
         }
     }
+
+    /// Keys of every `<key>.toml` in the directory, sorted, without loading
+    /// them.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<_> = read_dir(&self.base)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "toml"))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        keys.sort();
+        keys
+    }
 }
 
-impl<P: AsRef<Path>> ConfigurationLoader for DirectoryConfigurationLoader<P> {
-    async fn load(&mut self) -> Vec<Configuration> {
+impl<P: AsRef<Path> + Send> ConfigurationLoader for DirectoryConfigurationLoader<P> {
+    /// Load every `<key>.toml` in the directory that is new or whose
+    /// modification time changed since the previous load, and report those
+    /// deleted since as [`Configuration::removed`].
+    async fn load(&mut self) -> Vec<Result<Configuration, ConfigurationError>> {
         let mut loaded = Vec::new();
+        let mut present = HashSet::new();
         if let Ok(paths) = read_dir(&self.base) {
-            'files: for path in paths.flatten() {
-                if let Some(ext) = path.path().extension() {
-                    if ext == ".toml" {
-                        if let Some(key) = path.path().file_stem() {
-                            let key = key.to_str().unwrap().to_string();
-                            if let Ok(mut file) = File::open(path.path()) {
-                                let file_mtime = file
-                                    .metadata()
-                                    .map(|inner| inner.modified().ok())
-                                    .ok()
-                                    .flatten();
-                                let last_mtime = self.watched.get(&key).cloned();
-
-                                match (file_mtime, last_mtime) {
-                                    (Some(file), Some(last)) => {
-                                        if file == last {
-                                            continue 'files;
-                                        }
-                                    }
-                                    _ => {
-                                        todo!()
-                                    }
-                                }
-
-                                let mut payload = String::new();
-                                if file.read_to_string(&mut payload).is_ok() {
-                                    match payload.parse::<Table>() {
-                                        Ok(payload) => {
-                                            loaded.push(Configuration {
-                                                key: key.clone(),
-                                                content: payload.into(),
-                                            });
-                                            self.watched.insert(key, file_mtime.unwrap());
-                                        }
-                                        Err(_) => {}
-                                    }
-                                }
-                            }
-                        }
+            for path in paths.flatten() {
+                let path = path.path();
+                if path.extension().map_or(true, |ext| ext != "toml") {
+                    continue;
+                }
+                let Some(key) = path.file_stem().and_then(|key| key.to_str()) else {
+                    continue;
+                };
+                let key = key.to_string();
+                present.insert(key.clone());
+
+                if let Ok(mut file) = File::open(&path) {
+                    let file_mtime = file
+                        .metadata()
+                        .and_then(|inner| inner.modified())
+                        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+                    if self.watched.get(&key) == Some(&file_mtime) {
+                        continue;
                     }
+
+                    // remember the mtime even when the file is broken, so a
+                    // bad edit is reported once rather than on every load.
+                    self.watched.insert(key.clone(), file_mtime);
+
+                    let mut payload = String::new();
+                    let parsed = file
+                        .read_to_string(&mut payload)
+                        .map_err(|err| err.to_string())
+                        .and_then(|_| payload.parse::<Table>().map_err(|err| err.to_string()));
+
+                    loaded.push(match parsed {
+                        Ok(payload) => Ok(Configuration {
+                            key,
+                            path: Some(path),
+                            content: payload.into(),
+                            removed: false,
+                        }),
+                        Err(message) => Err(ConfigurationError {
+                            key,
+                            path: Some(path),
+                            field: None,
+                            message,
+                        }),
+                    });
                 }
             }
         }
+
+        let mut removed: Vec<_> = self
+            .watched
+            .keys()
+            .filter(|key| !present.contains(*key))
+            .cloned()
+            .collect();
+        removed.sort();
+        for key in removed {
+            self.watched.remove(&key);
+            let path = self.base.as_ref().join(format!("{}.toml", key));
+            loaded.push(Ok(Configuration::removed(&key, Some(path))));
+        }
         loaded
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn reloads_only_changed_files() {
+        let base = std::env::temp_dir().join(format!("lattitude-config-{}", std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("accuweather.toml"), "api_key = \"one\"").unwrap();
+        fs::write(base.join("notes.txt"), "ignored").unwrap();
+
+        let mut loader = DirectoryConfigurationLoader::new(&base);
+
+        let loaded = loader.load().await;
+        assert_eq!(loaded.len(), 1);
+        let loaded = loaded[0].as_ref().unwrap();
+        assert_eq!(loaded.key, "accuweather");
+        assert_eq!(loaded.path, Some(base.join("accuweather.toml")));
+        assert_eq!(loaded.content["api_key"].as_str(), Some("one"));
+
+        assert!(loader.load().await.is_empty());
+
+        let file = File::options()
+            .write(true)
+            .open(base.join("accuweather.toml"))
+            .unwrap();
+        fs::write(base.join("accuweather.toml"), "api_key = \"two\"").unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+
+        let loaded = loader.load().await;
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(
+            loaded[0].as_ref().unwrap().content["api_key"].as_str(),
+            Some("two")
+        );
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn reports_deleted_files_once() {
+        let base = std::env::temp_dir().join(format!("lattitude-deleted-{}", std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("birdnet.toml"), "keep = 1").unwrap();
+
+        let mut loader = DirectoryConfigurationLoader::new(&base);
+        assert!(!loader.load().await[0].as_ref().unwrap().removed);
+
+        fs::remove_file(base.join("birdnet.toml")).unwrap();
+        let loaded = loader.load().await;
+        assert_eq!(loaded.len(), 1);
+        let loaded = loaded[0].as_ref().unwrap();
+        assert!(loaded.removed);
+        assert_eq!(loaded.key, "birdnet");
+        assert_eq!(loaded.path, Some(base.join("birdnet.toml")));
+
+        assert!(loader.load().await.is_empty());
+
+        // recreated, it loads as new
+        fs::write(base.join("birdnet.toml"), "keep = 2").unwrap();
+        let loaded = loader.load().await;
+        assert!(!loaded[0].as_ref().unwrap().removed);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn reports_invalid_toml_once() {
+        let base = std::env::temp_dir().join(format!("lattitude-broken-{}", std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("birdnet.toml"), "keep = ").unwrap();
+
+        let mut loader = DirectoryConfigurationLoader::new(&base);
+
+        let loaded = loader.load().await;
+        assert_eq!(loaded.len(), 1);
+        let err = loaded[0].as_ref().unwrap_err();
+        assert_eq!(err.key, "birdnet");
+        assert_eq!(err.path, Some(base.join("birdnet.toml")));
+
+        assert!(loader.load().await.is_empty());
+
+        fs::remove_dir_all(&base).ok();
+    }
+}
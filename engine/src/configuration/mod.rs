@@ -1,12 +1,141 @@
-mod directory;
+pub mod directory;
+pub mod secret;
 
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::path::PathBuf;
 use toml::Value;
 
+#[derive(Clone, Debug)]
 pub struct Configuration {
-    key: String,
-    content: Value,
+    pub key: String,
+    pub path: Option<PathBuf>,
+    pub content: Value,
+    /// The configuration's source is gone, e.g. its file was deleted; the
+    /// integration is reconfigured without one.
+    pub removed: bool,
 }
 
+impl Configuration {
+    pub fn new(key: &str, content: Value) -> Self {
+        Self {
+            key: key.to_string(),
+            path: None,
+            content,
+            removed: false,
+        }
+    }
+
+    /// Marks `key`'s configuration, last loaded from `path`, as gone.
+    pub fn removed(key: &str, path: Option<PathBuf>) -> Self {
+        Self {
+            key: key.to_string(),
+            path,
+            content: Value::Table(Default::default()),
+            removed: true,
+        }
+    }
+
+    /// Resolve secret references and deserialize the content, reporting the
+    /// offending field on failure.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, ConfigurationError> {
+        let content = secret::resolve(&self.content).map_err(|err| ConfigurationError {
+            key: self.key.clone(),
+            path: self.path.clone(),
+            field: Some(err.field),
+            message: err.message,
+        })?;
+
+        serde_path_to_error::deserialize(content).map_err(|err| {
+            let field = err.path().to_string();
+            ConfigurationError {
+                key: self.key.clone(),
+                path: self.path.clone(),
+                field: if field == "." { None } else { Some(field) },
+                message: err.into_inner().to_string(),
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigurationError {
+    pub key: String,
+    pub path: Option<PathBuf>,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl Display for ConfigurationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}", path.display())?;
+        } else {
+            write!(f, "{}", self.key)?;
+        }
+        if let Some(field) = &self.field {
+            write!(f, ": {}", field)?;
+        }
+        write!(f, ": {}", self.message.trim_end())
+    }
+}
+
+impl Error for ConfigurationError {}
+
 pub trait ConfigurationLoader {
-    async fn load(&mut self) -> Vec<Configuration>;
+    fn load(
+        &mut self,
+    ) -> impl Future<Output = Vec<Result<Configuration, ConfigurationError>>> + Send;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+    use toml::toml;
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Station {
+        token: String,
+        keep: usize,
+    }
+
+    #[test]
+    fn deserialize_error_names_field() {
+        let mut configuration = Configuration::new(
+            "birdnet",
+            toml! {
+                token = "abc"
+                keep = "ten"
+            }
+            .into(),
+        );
+        configuration.path = Some(PathBuf::from("/etc/lattitude/birdnet.toml"));
+
+        let err = configuration.deserialize::<Station>().unwrap_err();
+        assert_eq!(err.key, "birdnet");
+        assert_eq!(err.field.as_deref(), Some("keep"));
+        assert!(err.message.contains("invalid type"));
+        assert!(err
+            .to_string()
+            .starts_with("/etc/lattitude/birdnet.toml: keep: invalid type"));
+    }
+
+    #[test]
+    fn deserialize_error_missing_field() {
+        let configuration = Configuration::new(
+            "birdnet",
+            toml! {
+                keep = 10
+            }
+            .into(),
+        );
+
+        let err = configuration.deserialize::<Station>().unwrap_err();
+        assert_eq!(err.field, None);
+        assert!(err.message.contains("token"));
+    }
 }
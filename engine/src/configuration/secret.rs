@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt::{Debug, Display, Formatter};
+use std::fs;
+use toml::Value;
+
+const REDACTED: &str = "********";
+const SECRET_ENV: &str = "env";
+const SECRET_FILE: &str = "file";
+
+/// A configuration value that must never show up in logs or dumps.
+///
+/// Deserializes transparently as the inner value, but formats and
+/// serializes as `********`; only [`Secret::expose`] yields the value.
+/// Pair with a secret reference such as
+/// `api_key = { env = "ACCUWEATHER_KEY" }` to keep it out of the
+/// configuration file altogether.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(inner: T) -> Self {
+        Self(inner)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Debug for Secret<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl<T> Display for Secret<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+pub(crate) struct SecretError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Replace every secret reference in `value` with the secret it names.
+///
+/// A secret reference is a table holding nothing but an `env` or a `file`
+/// string, e.g. `{ env = "ACCUWEATHER_KEY" }` or
+/// `{ file = "/run/secrets/accuweather" }`. Any other table is plain data.
+pub(crate) fn resolve(value: &Value) -> Result<Value, SecretError> {
+    resolve_at(value, "")
+}
+
+fn resolve_at(value: &Value, field: &str) -> Result<Value, SecretError> {
+    match value {
+        Value::Table(table) => {
+            if table.len() == 1 {
+                let error = |message: String| SecretError {
+                    field: field.to_string(),
+                    message,
+                };
+                match table.iter().next() {
+                    Some((kind, Value::String(name))) if kind == SECRET_ENV => {
+                        return std::env::var(name).map(Value::String).map_err(|_| {
+                            error(format!("environment variable {} is not set", name))
+                        });
+                    }
+                    Some((kind, Value::String(path))) if kind == SECRET_FILE => {
+                        return fs::read_to_string(path)
+                            .map(|secret| {
+                                Value::String(secret.trim_end_matches(['\r', '\n']).to_string())
+                            })
+                            .map_err(|err| error(format!("unable to read {}: {}", path, err)));
+                    }
+                    _ => {}
+                }
+            }
+
+            let mut resolved = toml::Table::new();
+            for (key, inner) in table {
+                let path = if field.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", field, key)
+                };
+                resolved.insert(key.clone(), resolve_at(inner, &path)?);
+            }
+            Ok(Value::Table(resolved))
+        }
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(index, inner)| resolve_at(inner, &format!("{}[{}]", field, index)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        _ => Ok(value.clone()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::configuration::Configuration;
+    use toml::toml;
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    struct Station {
+        token: Secret<String>,
+        keep: usize,
+    }
+
+    #[test]
+    fn resolve_env_reference() {
+        std::env::set_var("LATTITUDE_TEST_BIRDNET_TOKEN", "s3cr3t");
+        let configuration = Configuration::new(
+            "birdnet",
+            toml! {
+                token = { env = "LATTITUDE_TEST_BIRDNET_TOKEN" }
+                keep = 10
+            }
+            .into(),
+        );
+
+        let station = configuration.deserialize::<Station>().unwrap();
+        assert_eq!(station.token.expose(), "s3cr3t");
+        assert_eq!(station.keep, 10);
+
+        // never leaks through formatting
+        assert!(!format!("{:?}", station).contains("s3cr3t"));
+        assert!(!station.token.to_string().contains("s3cr3t"));
+
+        // the configuration itself only ever holds the reference
+        assert!(!format!("{:?}", configuration).contains("s3cr3t"));
+    }
+
+    #[test]
+    fn resolve_file_reference() {
+        let path = std::env::temp_dir().join(format!("lattitude-secret-{}", std::process::id()));
+        fs::write(&path, "from-a-file\n").unwrap();
+
+        let mut table = toml::Table::new();
+        table.insert("keep".to_string(), Value::Integer(3));
+        let mut reference = toml::Table::new();
+        reference.insert(
+            SECRET_FILE.to_string(),
+            Value::String(path.display().to_string()),
+        );
+        table.insert("token".to_string(), reference.into());
+        let configuration = Configuration::new("birdnet", table.into());
+
+        let station = configuration.deserialize::<Station>().unwrap();
+        assert_eq!(station.token.expose(), "from-a-file");
+
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn missing_env_reference() {
+        let configuration = Configuration::new(
+            "birdnet",
+            toml! {
+                token = { env = "LATTITUDE_TEST_DOES_NOT_EXIST" }
+                keep = 10
+            }
+            .into(),
+        );
+
+        let err = configuration.deserialize::<Station>().unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("token"));
+        assert!(err.message.contains("LATTITUDE_TEST_DOES_NOT_EXIST"));
+    }
+
+    #[test]
+    fn serialization_is_redacted() {
+        let station = Station {
+            token: Secret::new("s3cr3t".to_string()),
+            keep: 4,
+        };
+        let dumped = toml::to_string(&station).unwrap();
+        assert!(!dumped.contains("s3cr3t"));
+        assert!(dumped.contains(REDACTED));
+        assert!(!serde_json::to_string(&station).unwrap().contains("s3cr3t"));
+    }
+
+    #[test]
+    fn tables_with_more_keys_are_not_references() {
+        #[derive(Debug, Deserialize)]
+        struct Deployment {
+            target: Target,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Target {
+            env: String,
+            region: String,
+        }
+
+        let configuration = Configuration::new(
+            "deploy",
+            toml! {
+                target = { env = "production", region = "eu" }
+            }
+            .into(),
+        );
+
+        let deployment = configuration.deserialize::<Deployment>().unwrap();
+        assert_eq!(deployment.target.env, "production");
+        assert_eq!(deployment.target.region, "eu");
+    }
+}
@@ -1,7 +1,16 @@
 use pixelfield::pixelfield::PixelField;
+use std::any::type_name;
 
 pub mod bmp;
+pub mod png;
 
 pub trait Display {
     fn display(&mut self, pixel_field: &PixelField);
+
+    /// Identifies the display in metrics, by default its type name.
+    fn name(&self) -> &str {
+        let name = type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name)
+    }
 }
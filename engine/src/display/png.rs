@@ -0,0 +1,75 @@
+use crate::display::Display;
+use pixelfield::pixelfield::PixelField;
+use std::sync::{Arc, Mutex};
+
+/// Keeps the most recent frame in memory, in greyscale like the e-paper
+/// panel, so it can be served as a PNG. Clones share the same frame.
+#[derive(Clone, Default)]
+pub struct FrameDisplay<const WIDTH: u32, const HEIGHT: u32> {
+    luma: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32> FrameDisplay<WIDTH, HEIGHT> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The most recent frame, or `None` before the first one.
+    pub fn png(&self) -> Option<Vec<u8>> {
+        let luma = self.luma.lock().unwrap();
+        Some(encode_greyscale(WIDTH, HEIGHT, luma.as_ref()?))
+    }
+}
+
+impl<const WIDTH: u32, const HEIGHT: u32> Display for FrameDisplay<WIDTH, HEIGHT> {
+    fn display(&mut self, pixel_field: &PixelField) {
+        // unset pixels are white, as on the panel
+        let mut luma = vec![u8::MAX; (WIDTH * HEIGHT) as usize];
+        for pixel in pixel_field.iter() {
+            let point = pixel.point();
+            if (0..WIDTH as i32).contains(&point.x) && (0..HEIGHT as i32).contains(&point.y) {
+                luma[(point.y as u32 * WIDTH + point.x as u32) as usize] = pixel.color().luma();
+            }
+        }
+        self.luma.lock().unwrap().replace(luma);
+    }
+}
+
+/// Encode 8-bit greyscale pixels, row by row, as a PNG.
+pub fn encode_greyscale(width: u32, height: u32, luma: &[u8]) -> Vec<u8> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    // writing to memory only fails on a size mismatch, which `display` rules out
+    let mut writer = encoder.write_header().expect("PNG header");
+    writer.write_image_data(luma).expect("PNG image data");
+    writer.finish().expect("PNG end");
+    png
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pixelfield::color::Rgb;
+
+    #[test]
+    fn latest_frame_as_png() {
+        let display = FrameDisplay::<3, 2>::new();
+        assert!(display.png().is_none());
+
+        let mut pixel_field = PixelField::default();
+        pixel_field.set((1, 0), Rgb { r: 0, g: 0, b: 0 }.into());
+        pixel_field.set((7, 7), Rgb { r: 0, g: 0, b: 0 }.into());
+        display.clone().display(&pixel_field);
+
+        let png = display.png().unwrap();
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut luma = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut luma).unwrap();
+        assert_eq!((frame.width, frame.height), (3, 2));
+        assert_eq!(frame.color_type, png::ColorType::Grayscale);
+        assert_eq!(frame.bit_depth, png::BitDepth::Eight);
+        assert_eq!(luma[..frame.buffer_size()], [255, 0, 255, 255, 255, 255]);
+    }
+}
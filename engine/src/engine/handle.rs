@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
+
+/// Controllers to update right away, see [`EngineHandle::refresh`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Refresh {
+    /// Instance key, or an integration key to refresh all its instances.
+    /// `None` refreshes every integration.
+    pub integration: Option<String>,
+    /// Controller name, i.e. its discriminant's `Debug` output. `None`
+    /// refreshes every controller of the integration.
+    pub controller: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct EngineHandle {
+    shutdown: Arc<watch::Sender<bool>>,
+    refresh: mpsc::UnboundedSender<Refresh>,
+}
+
+impl EngineHandle {
+    pub(crate) fn new(
+        shutdown: Arc<watch::Sender<bool>>,
+        refresh: mpsc::UnboundedSender<Refresh>,
+    ) -> Self {
+        Self { shutdown, refresh }
+    }
+
+    /// Ask a running [`Engine`](crate::engine::Engine) to stop, cancelling
+    /// any update still in flight.
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    /// Update the integration's controllers now, regardless of schedule,
+    /// backoff or quiet hours, e.g. after fixing its API key. The
+    /// schedule starts over from this update.
+    pub fn refresh(&self, integration: &str, controller: Option<&str>) {
+        self.send(Refresh {
+            integration: Some(integration.to_string()),
+            controller: controller.map(ToString::to_string),
+        });
+    }
+
+    /// Update every controller of every integration now.
+    pub fn refresh_all(&self) {
+        self.send(Refresh {
+            integration: None,
+            controller: None,
+        });
+    }
+
+    fn send(&self, refresh: Refresh) {
+        // only fails once the engine is gone, when there is nothing to refresh
+        self.refresh.send(refresh).ok();
+    }
+}
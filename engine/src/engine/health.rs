@@ -0,0 +1,135 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Option<Duration>,
+    pub jitter: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::seconds(30),
+            max: None,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before the next attempt after `failures` consecutive failures.
+    ///
+    /// Doubles from `initial`, jittered, and never exceeds `max` nor the
+    /// controller's own cadence, so a failing controller is never polled
+    /// less often than a healthy one.
+    pub fn delay(&self, failures: u32, cadence: Duration) -> Duration {
+        let exponent = failures.saturating_sub(1).min(16);
+        let mut delay = self.initial * 2_i32.pow(exponent);
+
+        if self.jitter > 0.0 {
+            let factor = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+            delay = Duration::milliseconds((delay.num_milliseconds() as f64 * factor) as i64);
+        }
+
+        if let Some(max) = self.max {
+            delay = delay.min(max);
+        }
+        delay.min(cadence)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Health {
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+}
+
+impl Health {
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures == 0
+    }
+
+    pub(crate) fn record_success(&mut self, when: DateTime<Utc>) {
+        self.last_success.replace(when);
+        self.consecutive_failures = 0;
+    }
+
+    pub(crate) fn record_failure(&mut self, when: DateTime<Utc>, error: String) {
+        self.last_failure.replace(when);
+        self.last_error.replace(error);
+        self.consecutive_failures += 1;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ControllerHealth {
+    pub controller: String,
+    /// `None` for a cron schedule that never matches.
+    pub cadence: Option<Duration>,
+    /// `None` if the controller is never due again.
+    pub next_update: Option<DateTime<Utc>>,
+    pub health: Health,
+}
+
+#[derive(Clone, Debug)]
+pub struct IntegrationHealth {
+    pub key: String,
+    pub name: String,
+    /// Whether the integration is enabled in its configuration.
+    pub enabled: bool,
+    pub controllers: Vec<ControllerHealth>,
+}
+
+impl IntegrationHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.controllers
+            .iter()
+            .all(|inner| inner.health.is_healthy())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_cadence() {
+        let backoff = Backoff {
+            initial: Duration::seconds(10),
+            max: None,
+            jitter: 0.0,
+        };
+        let cadence = Duration::minutes(1);
+
+        assert_eq!(backoff.delay(1, cadence), Duration::seconds(10));
+        assert_eq!(backoff.delay(2, cadence), Duration::seconds(20));
+        assert_eq!(backoff.delay(3, cadence), Duration::seconds(40));
+        assert_eq!(backoff.delay(4, cadence), cadence);
+        assert_eq!(backoff.delay(40, cadence), cadence);
+    }
+
+    #[test]
+    fn backoff_jitter_stays_in_bounds() {
+        let backoff = Backoff {
+            initial: Duration::seconds(10),
+            max: Some(Duration::seconds(15)),
+            jitter: 0.5,
+        };
+
+        for failures in 1..10 {
+            let delay = backoff.delay(failures, Duration::hours(1));
+            assert!(delay <= Duration::seconds(15));
+            assert!(delay >= Duration::seconds(5));
+        }
+
+        // jitter never pushes past the cadence either
+        let cadence = Duration::seconds(12);
+        for failures in 1..10 {
+            assert!(backoff.delay(failures, cadence) <= cadence);
+        }
+    }
+}
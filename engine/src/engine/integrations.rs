@@ -1,76 +1,237 @@
+use crate::clock::{SharedClock, SystemClock};
+use crate::configuration::{Configuration, ConfigurationError};
+use crate::engine::health::{Backoff, ControllerHealth, Health, IntegrationHealth};
+use crate::engine::schedule::{
+    split_overrides, QuietHours, Schedule, ScheduleOverride, DEFAULT_CONTROLLER,
+};
 use crate::global_configuration::GlobalConfiguration;
-use crate::integration::Integration;
-use crate::model::{Model, ModelManager};
+use crate::integration::{Integration, IntegrationInfo};
+use crate::metrics::Metrics;
+use crate::model::{Model, ModelKey, ModelManager, Retention};
 use chrono::{DateTime, Duration, Utc};
-use serde::Deserialize;
-use std::any::TypeId;
+use chrono_tz::Tz;
+use futures::future::join_all;
+use futures::FutureExt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::future::Future;
 use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use toml::Value;
+use tokio::task::JoinHandle;
 
-pub trait ManageableIntegration {
+pub trait ManageableIntegration: Send + Sync {
+    fn info(&self) -> &IntegrationInfo;
+
+    /// The instance key: [`IntegrationInfo::key`], or `<key>.<name>` for a
+    /// named instance.
+    fn key(&self) -> &str;
+
+    /// Configure the integration. Passing `None` re-applies the most recent
+    /// configuration, e.g. after the global configuration changed.
     fn configure<'r>(
         &'r self,
         world: &'r GlobalConfiguration,
-        configuration: Option<toml::Value>,
-    ) -> Pin<Box<dyn Future<Output = ()> + 'r>>;
+        configuration: Option<Configuration>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), ConfigurationError>> + Send + 'r>>;
+
+    /// Start an update of every due controller, each in its own task, and
+    /// return without waiting for them.
+    fn update<'r>(
+        &'r self,
+        clock: &'r SharedClock,
+        metrics: &'r Metrics,
+    ) -> Pin<Box<dyn Future<Output = Vec<JoinHandle<Update>>> + Send + 'r>>;
+
+    fn health<'r>(
+        &'r self,
+        now: DateTime<Utc>,
+    ) -> Pin<Box<dyn Future<Output = IntegrationHealth> + Send + 'r>>;
 
-    fn update<'r>(&'r self) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'r>>;
+    /// Make the named controller, or all of them, due now. Returns whether
+    /// any controller matched.
+    fn refresh<'r>(
+        &'r self,
+        controller: Option<&'r str>,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'r>>;
+
+    /// Stop the integration if it was started, e.g. on shutdown.
+    fn stop<'r>(&'r self) -> Pin<Box<dyn Future<Output = ()> + Send + 'r>>;
+
+    /// The earliest instant at which any controller of this integration is due,
+    /// or `None` if the integration registered no controllers or is disabled.
+    fn next_update<'r>(
+        &'r self,
+        now: DateTime<Utc>,
+    ) -> Pin<Box<dyn Future<Output = Option<DateTime<Utc>>> + Send + 'r>>;
 }
 
 pub struct IntegrationHolder<I>
 where
     I: Integration,
 {
+    info: IntegrationInfo,
+    key: String,
     integration: Arc<Mutex<I>>,
+    configuration: Mutex<Option<Configuration>>,
     updates: Arc<Mutex<HashMap<I::Discriminant, UpdateEntry>>>,
+    models: Vec<ClearModel>,
+    when_disabled: WhenDisabled,
+    enabled: AtomicBool,
+    started: Arc<AtomicBool>,
 }
 
 impl<I> IntegrationHolder<I>
 where
     I: Integration,
 {
-    pub fn new(integration: I, cadences: HashMap<I::Discriminant, Duration>) -> Self {
+    pub fn new(key: String, integration: I, context: IntegrationContext<I>) -> Self {
         Self {
+            info: I::info(),
+            key,
             integration: Arc::new(Mutex::new(integration)),
-            updates: Arc::new(Mutex::new(
-                cadences
-                    .iter()
-                    .map(|(discriminant, cadence)| (*discriminant, UpdateEntry::new(*cadence)))
-                    .collect(),
-            )),
+            configuration: Default::default(),
+            updates: Arc::new(Mutex::new(context.updates)),
+            models: context.models,
+            when_disabled: context.when_disabled,
+            enabled: AtomicBool::new(true),
+            started: Default::default(),
+        }
+    }
+
+    /// Stop the integration, with its lock held.
+    async fn stop_locked(&self, integration: &mut I) {
+        if self.started.swap(false, Ordering::AcqRel) {
+            log::info!("stopping {}", self.key);
+            integration.stop().await;
         }
     }
 }
 
+type ClearModel = Box<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// What happens to an integration's models while it is disabled through
+/// `enabled = false` in its configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WhenDisabled {
+    /// Keep showing the last values, until they go stale.
+    #[default]
+    Retain,
+    /// Clear the models, so pages show nothing rather than old data.
+    Clear,
+}
+
+/// Configuration keys every integration accepts besides its own.
+#[derive(Deserialize)]
+struct Lifecycle {
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+    when_disabled: Option<WhenDisabled>,
+}
+
+const LIFECYCLE_KEYS: [&str; 2] = ["enabled", "when_disabled"];
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// How long an update may take unless the integration or its configuration
+/// says otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::minutes(1);
+
 pub struct UpdateEntry {
-    cadence: Duration,
-    last_update: Option<DateTime<Utc>>,
+    registered: (Schedule, Option<QuietHours>, Duration),
+    schedule: Schedule,
+    quiet_hours: Option<QuietHours>,
+    timezone: Tz,
+    timeout: Duration,
+    refresh: bool,
+    running: bool,
+    backoff: Backoff,
+    last_attempt: Option<DateTime<Utc>>,
+    retry_delay: Option<Duration>,
+    health: Health,
 }
 
 impl UpdateEntry {
-    fn new(cadence: Duration) -> Self {
+    fn new(schedule: Schedule) -> Self {
         Self {
-            cadence,
-            last_update: None,
+            registered: (schedule.clone(), None, DEFAULT_TIMEOUT),
+            schedule,
+            quiet_hours: None,
+            timezone: Tz::UTC,
+            timeout: DEFAULT_TIMEOUT,
+            refresh: false,
+            running: false,
+            backoff: Default::default(),
+            last_attempt: None,
+            retry_delay: None,
+            health: Default::default(),
         }
     }
 
-    fn should_update(&self) -> bool {
-        if let Some(last_update) = self.last_update {
-            (Utc::now() - last_update) > self.cadence
-        } else {
-            true
+    /// When the controller is next due, or `None` if its schedule never
+    /// matches again.
+    fn next_update(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.refresh {
+            return Some(now);
         }
+        let due = match (self.last_attempt, self.retry_delay) {
+            (Some(last_attempt), Some(retry_delay)) => last_attempt + retry_delay,
+            (Some(last_attempt), None) => self.schedule.next_after(last_attempt, self.timezone)?,
+            (None, _) => now,
+        };
+        Some(
+            self.quiet_hours
+                .map_or(due, |quiet_hours| quiet_hours.defer(due, self.timezone)),
+        )
+    }
+
+    /// Apply the configured override, or fall back to the schedule the
+    /// integration registered.
+    fn reschedule(&mut self, timezone: Tz, schedule: Option<&ScheduleOverride>) {
+        let (registered, quiet_hours, timeout) = self.registered.clone();
+        self.timezone = timezone;
+        self.schedule = schedule
+            .and_then(ScheduleOverride::schedule)
+            .unwrap_or(registered);
+        self.quiet_hours = schedule.and_then(|inner| inner.quiet_hours).or(quiet_hours);
+        self.timeout = schedule
+            .and_then(ScheduleOverride::timeout)
+            .unwrap_or(timeout);
+    }
+
+    /// Whether the controller is due and not still running.
+    fn should_update(&self, now: DateTime<Utc>) -> bool {
+        !self.running && self.next_update(now).map_or(false, |due| due <= now)
     }
 
-    fn mark_updated(&mut self) {
-        self.last_update.replace(Utc::now());
+    fn mark_updated(&mut self, now: DateTime<Utc>) {
+        self.running = false;
+        self.last_attempt.replace(now);
+        self.retry_delay.take();
+        self.health.record_success(now);
+    }
+
+    fn mark_failed(&mut self, now: DateTime<Utc>, error: String) {
+        self.running = false;
+        self.last_attempt.replace(now);
+        self.health.record_failure(now, error);
+        // a schedule that never matches again leaves only the backoff
+        self.retry_delay.replace(
+            self.backoff.delay(
+                self.health.consecutive_failures,
+                self.schedule
+                    .period(now, self.timezone)
+                    .unwrap_or(Duration::max_value()),
+            ),
+        );
     }
 }
 
@@ -78,69 +239,422 @@ impl<I> ManageableIntegration for IntegrationHolder<I>
 where
     I: Integration,
 {
+    fn info(&self) -> &IntegrationInfo {
+        &self.info
+    }
+
+    fn key(&self) -> &str {
+        &self.key
+    }
+
     fn configure<'r>(
         &'r self,
         global_configuration: &'r GlobalConfiguration,
-        configuration: Option<Value>,
-    ) -> Pin<Box<dyn Future<Output = ()> + 'r>> {
+        configuration: Option<Configuration>,
+    ) -> Pin<Box<dyn Future<Output = Result<(), ConfigurationError>> + Send + 'r>> {
         let controller = self.integration.clone();
         Box::pin(async move {
+            let mut current = self.configuration.lock().await;
+            let raw = match configuration {
+                Some(configuration) if configuration.removed => None,
+                Some(configuration) => Some(configuration),
+                None => current.clone(),
+            };
+
+            let mut updates = self.updates.lock().await;
+            let controllers: Vec<_> = updates
+                .keys()
+                .map(|discriminant| format!("{:?}", discriminant))
+                .collect();
+
+            // deserialize before locking, so a broken file leaves the
+            // integration running with its previous configuration.
+            let (configuration, schedules, lifecycle) = match &raw {
+                Some(raw) => {
+                    let lifecycle = raw.deserialize::<Lifecycle>()?;
+                    let (mut configuration, schedules) = split_overrides(raw, &controllers)?;
+                    if let Some(table) = configuration.content.as_table_mut() {
+                        for key in LIFECYCLE_KEYS {
+                            table.remove(key);
+                        }
+                    }
+                    (
+                        Some(configuration.deserialize::<I::Configuration>()?),
+                        schedules,
+                        Some(lifecycle),
+                    )
+                }
+                None => (None, HashMap::new(), None),
+            };
+
+            for (discriminant, entry) in updates.iter_mut() {
+                let schedule = schedules
+                    .get(&format!("{:?}", discriminant))
+                    .or_else(|| schedules.get(DEFAULT_CONTROLLER));
+                entry.reschedule(global_configuration.timezone, schedule);
+            }
+            drop(updates);
+
             let mut integration = controller.lock().await;
-            if let Some(configuration) = configuration {
-                if let Ok(config) = <I as Integration>::Configuration::deserialize(configuration) {
-                    integration
-                        .configure(global_configuration.clone(), Some(config))
-                        .await;
-                } else {
-                    integration
-                        .configure(global_configuration.clone(), None)
-                        .await;
+            integration
+                .configure(global_configuration.clone(), configuration)
+                .await;
+            *current = raw;
+
+            let enabled = lifecycle.as_ref().map_or(true, |inner| inner.enabled);
+            if self.enabled.swap(enabled, Ordering::AcqRel) && !enabled {
+                log::info!("disabling {}", self.key);
+                self.stop_locked(&mut integration).await;
+
+                let when_disabled = lifecycle
+                    .and_then(|inner| inner.when_disabled)
+                    .unwrap_or(self.when_disabled);
+                if when_disabled == WhenDisabled::Clear {
+                    for clear in &self.models {
+                        clear().await;
+                    }
                 }
-            } else {
-                integration
-                    .configure(global_configuration.clone(), None)
-                    .await;
             }
+            Ok(())
         })
     }
 
-    fn update<'r>(&'r self) -> Pin<Box<dyn Future<Output = ()> + Send + Sync + 'r>> {
+    fn update<'r>(
+        &'r self,
+        clock: &'r SharedClock,
+        metrics: &'r Metrics,
+    ) -> Pin<Box<dyn Future<Output = Vec<JoinHandle<Update>>> + Send + 'r>> {
         Box::pin(async move {
-            let mut updates = self.updates.lock().await;
+            if !self.enabled.load(Ordering::Acquire) {
+                return vec![];
+            }
+            let now = clock.now();
+            let due: Vec<_> = self
+                .updates
+                .lock()
+                .await
+                .iter_mut()
+                .filter(|(_, entry)| entry.should_update(now))
+                .map(|(discriminant, entry)| {
+                    // a refresh requested from here on runs it once more
+                    entry.refresh = false;
+                    entry.running = true;
+                    (*discriminant, entry.timeout)
+                })
+                .collect();
 
-            let mut integration = self.integration.lock().await;
+            due.into_iter()
+                .map(|(discriminant, timeout)| {
+                    tokio::spawn(run_update(
+                        self.key.clone(),
+                        self.integration.clone(),
+                        self.updates.clone(),
+                        self.started.clone(),
+                        discriminant,
+                        timeout,
+                        clock.clone(),
+                        metrics.clone(),
+                    ))
+                })
+                .collect()
+        })
+    }
 
-            for (discriminant, entry) in updates.iter_mut() {
-                if entry.should_update() {
-                    integration.update(*discriminant).await;
-                    entry.mark_updated();
+    fn health<'r>(
+        &'r self,
+        now: DateTime<Utc>,
+    ) -> Pin<Box<dyn Future<Output = IntegrationHealth> + Send + 'r>> {
+        Box::pin(async move {
+            let updates = self.updates.lock().await;
+            IntegrationHealth {
+                key: self.key.clone(),
+                name: self.info.name.clone(),
+                enabled: self.enabled.load(Ordering::Acquire),
+                controllers: updates
+                    .iter()
+                    .map(|(discriminant, entry)| ControllerHealth {
+                        controller: format!("{:?}", discriminant),
+                        cadence: entry.schedule.period(now, entry.timezone),
+                        next_update: entry.next_update(now),
+                        health: entry.health.clone(),
+                    })
+                    .collect(),
+            }
+        })
+    }
+
+    fn refresh<'r>(
+        &'r self,
+        controller: Option<&'r str>,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'r>> {
+        Box::pin(async move {
+            if !self.enabled.load(Ordering::Acquire) {
+                return false;
+            }
+            let mut matched = false;
+            for (discriminant, entry) in self.updates.lock().await.iter_mut() {
+                if controller.map_or(true, |inner| inner == format!("{:?}", discriminant)) {
+                    entry.refresh = true;
+                    matched = true;
                 }
             }
+            matched
+        })
+    }
+
+    fn stop<'r>(&'r self) -> Pin<Box<dyn Future<Output = ()> + Send + 'r>> {
+        Box::pin(async move {
+            let mut integration = self.integration.lock().await;
+            self.stop_locked(&mut integration).await;
+        })
+    }
+
+    fn next_update<'r>(
+        &'r self,
+        now: DateTime<Utc>,
+    ) -> Pin<Box<dyn Future<Output = Option<DateTime<Utc>>> + Send + 'r>> {
+        Box::pin(async move {
+            if !self.enabled.load(Ordering::Acquire) {
+                return None;
+            }
+            // a running controller is next due once it completes
+            self.updates
+                .lock()
+                .await
+                .values()
+                .filter(|entry| !entry.running)
+                .filter_map(|entry| entry.next_update(now))
+                .min()
         })
     }
 }
 
+/// Update one controller, starting the integration first if need be.
+///
+/// Controllers share the integration's `&mut self`, so those of one
+/// integration take turns; other integrations carry on meanwhile, and
+/// health stays readable while they run.
+#[allow(clippy::too_many_arguments)]
+async fn run_update<I: Integration>(
+    key: String,
+    integration: Arc<Mutex<I>>,
+    updates: Arc<Mutex<HashMap<I::Discriminant, UpdateEntry>>>,
+    started: Arc<AtomicBool>,
+    discriminant: I::Discriminant,
+    timeout: Duration,
+    clock: SharedClock,
+    metrics: Metrics,
+) -> Update {
+    let controller = format!("{:?}", discriminant);
+    let mut integration = integration.lock().await;
+
+    let begun = std::time::Instant::now();
+    let run = async {
+        if !started.load(Ordering::Acquire) {
+            log::info!("starting {}", key);
+            if let Err(err) = integration.start().await {
+                return Err(format!("start failed: {}", err));
+            }
+            started.store(true, Ordering::Release);
+        }
+        integration
+            .update(discriminant)
+            .await
+            .map_err(|err| err.to_string())
+    };
+    // a hung or panicking start or update counts as failed rather than
+    // running forever
+    let run = AssertUnwindSafe(run).catch_unwind();
+    let result = match tokio::time::timeout(timeout.to_std().unwrap_or_default(), run).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err("panicked".to_string()),
+        Err(_) => Err(format!("timed out after {}ms", timeout.num_milliseconds())),
+    };
+    drop(integration);
+
+    let now = clock.now();
+    metrics.record_update(
+        &key,
+        &controller,
+        begun.elapsed(),
+        result.is_ok().then_some(now),
+    );
+
+    if let Some(entry) = updates.lock().await.get_mut(&discriminant) {
+        match &result {
+            Ok(()) => entry.mark_updated(now),
+            Err(err) => {
+                log::warn!("{} {} update failed: {}", key, controller, err);
+                entry.mark_failed(now, err.clone());
+            }
+        }
+    }
+    Update {
+        integration: key,
+        controller,
+        error: result.err(),
+    }
+}
+
+/// A controller that ran, as reported by [`Integrations::update`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Update {
+    /// The instance key.
+    pub integration: String,
+    /// The discriminant's `Debug` output.
+    pub controller: String,
+    /// Why the update failed, if it did.
+    pub error: Option<String>,
+}
+
 struct IntegrationEntry {
     managed: Box<dyn ManageableIntegration>,
 }
 
-#[derive(Default)]
 pub struct Integrations {
     integrations: Vec<IntegrationEntry>,
+    clock: SharedClock,
+    metrics: Metrics,
+}
+
+impl Default for Integrations {
+    fn default() -> Self {
+        Self {
+            integrations: Default::default(),
+            clock: Arc::new(SystemClock),
+            metrics: Default::default(),
+        }
+    }
 }
 
 impl Integrations {
-    pub fn register<I>(&mut self, state_manager: &mut ModelManager, integration: I)
+    /// Schedule controllers by `clock` rather than the system clock.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
+    /// Record update counts and timings in `metrics`.
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = metrics;
+    }
+
+    /// Whether an integration is registered under the instance `key`.
+    pub fn contains(&self, key: &str) -> bool {
+        self.integrations
+            .iter()
+            .any(|inner| inner.managed.key() == key)
+    }
+
+    /// Register `integration` under the instance `key`, which also names its
+    /// configuration file and the provider of its models.
+    ///
+    /// Panics if the key is already taken.
+    pub fn register<I>(&mut self, state_manager: &mut ModelManager, key: String, mut integration: I)
     where
         I: Integration,
     {
-        let mut ctx = IntegrationContext::new(state_manager);
+        if self.contains(&key) {
+            panic!("integration {} is already registered", key);
+        }
+
+        let mut ctx = IntegrationContext::new(state_manager, &key, self.clock.clone());
         integration.integrate(&mut ctx);
-        let managed = IntegrationHolder::new(integration, ctx.cadences);
+        let managed = IntegrationHolder::new(key.clone(), integration, ctx);
         self.integrations.push(IntegrationEntry {
             managed: Box::new(managed),
         });
     }
+
+    /// Hand each integration the configuration whose key matches its
+    /// instance key. When `all` is set, integrations without a
+    /// matching configuration are reconfigured with their current one.
+    pub async fn configure(
+        &self,
+        global_configuration: &GlobalConfiguration,
+        mut configurations: Vec<Configuration>,
+        all: bool,
+    ) -> Vec<ConfigurationError> {
+        let mut errors = Vec::new();
+
+        for entry in &self.integrations {
+            let key = entry.managed.key();
+            let configuration = configurations
+                .iter()
+                .position(|inner| inner.key == key)
+                .map(|index| configurations.swap_remove(index));
+
+            if configuration.is_some() || all {
+                log::info!("configuring {}", key);
+                if let Err(err) = entry
+                    .managed
+                    .configure(global_configuration, configuration)
+                    .await
+                {
+                    errors.push(err);
+                }
+            }
+        }
+
+        for unmatched in configurations.iter().filter(|inner| !inner.removed) {
+            log::warn!("no integration for configuration {}", unmatched.key);
+        }
+
+        errors
+    }
+
+    /// Start updating every controller that is due, all concurrently,
+    /// and return the tasks doing so, each yielding what it ran.
+    pub async fn update(&self) -> Vec<JoinHandle<Update>> {
+        let mut tasks = Vec::new();
+        for entry in &self.integrations {
+            tasks.extend(entry.managed.update(&self.clock, &self.metrics).await);
+        }
+        tasks
+    }
+
+    /// Make matching controllers due now, see
+    /// [`EngineHandle::refresh`](crate::engine::handle::EngineHandle::refresh).
+    /// Returns whether any controller matched.
+    pub async fn refresh(&self, integration: Option<&str>, controller: Option<&str>) -> bool {
+        let mut matched = false;
+        for entry in &self.integrations {
+            let key = entry.managed.key();
+            let selected = integration.map_or(true, |integration| {
+                key == integration
+                    || key
+                        .strip_prefix(integration)
+                        .map_or(false, |instance| instance.starts_with('.'))
+            });
+            if selected {
+                matched |= entry.managed.refresh(controller).await;
+            }
+        }
+        matched
+    }
+
+    /// Stop every started integration, e.g. on shutdown.
+    pub async fn stop(&self) {
+        join_all(self.integrations.iter().map(|entry| entry.managed.stop())).await;
+    }
+
+    /// The earliest instant at which any registered controller is due.
+    pub async fn next_update(&self) -> Option<DateTime<Utc>> {
+        let mut next: Option<DateTime<Utc>> = None;
+        for entry in &self.integrations {
+            if let Some(candidate) = entry.managed.next_update(self.clock.now()).await {
+                next = Some(next.map_or(candidate, |next| next.min(candidate)));
+            }
+        }
+        next
+    }
+
+    pub async fn health(&self) -> Vec<IntegrationHealth> {
+        let mut health = Vec::new();
+        for entry in &self.integrations {
+            health.push(entry.managed.health(self.clock.now()).await);
+        }
+        health
+    }
 }
 
 pub struct IntegrationContext<'ctx, I>
@@ -148,46 +662,171 @@ where
     I: Integration,
 {
     model_manager: &'ctx mut ModelManager,
-    cadences: HashMap<I::Discriminant, Duration>,
+    key: &'ctx str,
+    clock: SharedClock,
+    updates: HashMap<I::Discriminant, UpdateEntry>,
+    models: Vec<ClearModel>,
+    when_disabled: WhenDisabled,
 }
 
 impl<'ctx, I: Integration> IntegrationContext<'ctx, I> {
-    fn new(state_manager: &'ctx mut ModelManager) -> Self {
+    fn new(state_manager: &'ctx mut ModelManager, key: &'ctx str, clock: SharedClock) -> Self {
         Self {
             model_manager: state_manager,
-            cadences: Default::default(),
+            key,
+            clock,
+            updates: Default::default(),
+            models: Default::default(),
+            when_disabled: Default::default(),
         }
     }
-    pub fn register_controller(&mut self, discriminant: I::Discriminant, cadence: Duration) {
-        self.cadences.insert(discriminant, cadence);
+
+    /// The instance key the integration is registered under.
+    pub fn key(&self) -> &str {
+        self.key
+    }
+
+    /// The clock the engine schedules by, for an integration to keep and
+    /// take the current time from instead of the system clock.
+    pub fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    /// Register a controller updated on `schedule`, either a fixed
+    /// [`Duration`] or a [`Schedule::Cron`]. The integration's configuration
+    /// may override it, see [`Schedule`].
+    pub fn register_controller<S: Into<Schedule>>(
+        &mut self,
+        discriminant: I::Discriminant,
+        schedule: S,
+    ) -> ControllerRegistration {
+        let schedule = schedule.into();
+        let entry = self
+            .updates
+            .entry(discriminant)
+            .or_insert_with(|| UpdateEntry::new(schedule.clone()));
+        entry.registered.0 = schedule.clone();
+        entry.schedule = schedule;
+        ControllerRegistration { entry }
+    }
+
+    /// What happens to the integration's models while it is disabled,
+    /// unless its configuration says otherwise with `when_disabled`.
+    pub fn when_disabled(&mut self, when_disabled: WhenDisabled) {
+        self.when_disabled = when_disabled;
     }
 
     pub fn register_model<T>(&mut self, state: Model<T>) -> ModelRegistration<T>
     where
         T: Clone + Debug + Sync + Send + 'static,
     {
-        self.model_manager.register(TypeId::of::<I>(), state);
-        ModelRegistration {
-            model_manager: self.model_manager,
-            _marker: Default::default(),
-        }
+        let model = state.clone();
+        self.models.push(Box::new(move || {
+            let model = model.clone();
+            Box::pin(async move { model.clear().await })
+        }));
+        self.model_manager.register(self.key, state);
+        ModelRegistration::new(self.model_manager, self.key)
+    }
+}
+
+pub struct ControllerRegistration<'ctx> {
+    entry: &'ctx mut UpdateEntry,
+}
+
+impl ControllerRegistration<'_> {
+    pub fn backoff(self, backoff: Backoff) -> Self {
+        self.entry.backoff = backoff;
+        self
+    }
+
+    /// Pause the controller daily during `quiet_hours`, in the configured
+    /// timezone.
+    pub fn quiet_hours(self, quiet_hours: QuietHours) -> Self {
+        self.entry.registered.1.replace(quiet_hours);
+        self.entry.quiet_hours.replace(quiet_hours);
+        self
+    }
+
+    /// Cancel an update that takes longer than `timeout`, recording it as a
+    /// failure. Defaults to one minute; the configuration may override it,
+    /// see [`Schedule`].
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.entry.registered.2 = timeout;
+        self.entry.timeout = timeout;
+        self
     }
 }
 
 pub struct ModelRegistration<'ctx, M> {
     model_manager: &'ctx mut ModelManager,
+    provider: String,
     _marker: PhantomData<M>,
 }
 
-impl<M> ModelRegistration<'_, M>
+impl<'ctx, M> ModelRegistration<'ctx, M>
 where
     M: Debug + Clone + Sync + Send + 'static,
 {
+    pub(crate) fn new(model_manager: &'ctx mut ModelManager, provider: &str) -> Self {
+        Self {
+            model_manager,
+            provider: provider.to_string(),
+            _marker: Default::default(),
+        }
+    }
+
     pub fn provides<Output>(self) -> Self
     where
-        Output: Debug + From<M> + 'static,
+        Output: Debug + From<M> + Send + 'static,
     {
         self.model_manager.provides::<M, Output>();
         self
     }
+
+    pub fn try_provides<Output>(self) -> Self
+    where
+        Output: Debug + TryFrom<M> + Send + 'static,
+    {
+        self.model_manager.try_provides::<M, Output>();
+        self
+    }
+
+    /// The key under which pages find this model.
+    pub fn key(&self) -> ModelKey<M> {
+        ModelKey::provided_by(self.provider.as_str())
+    }
+
+    /// Snapshot the model across restarts as `name`, unique within the
+    /// integration. Only takes effect when the engine has a state directory.
+    pub fn persist(self, name: &str) -> Self
+    where
+        M: Serialize + DeserializeOwned,
+    {
+        self.model_manager.persist::<M>(&self.provider, name);
+        self
+    }
+
+    /// Show the model as JSON in [`ModelManager::describe`]. Persisted
+    /// models are serializable already.
+    pub fn serializable(self) -> Self
+    where
+        M: Serialize,
+    {
+        self.model_manager.serializable::<M>(&self.provider);
+        self
+    }
+
+    /// Keep past values of the model, see [`ModelManager::history`].
+    pub fn history(self, retention: Retention) -> Self {
+        self.model_manager
+            .set_retention::<M>(&self.provider, retention);
+        self
+    }
+
+    /// How long the model stays fresh after each update.
+    pub fn max_age(self, max_age: Duration) -> Self {
+        self.model_manager.set_max_age::<M>(&self.provider, max_age);
+        self
+    }
 }
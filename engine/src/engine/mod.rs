@@ -1,41 +1,478 @@
+pub mod handle;
+pub mod health;
 pub mod integrations;
+pub mod registry;
+pub mod schedule;
 
-use crate::engine::integrations::Integrations;
+use crate::clock::{Clock, SharedClock, SystemClock};
+use crate::configuration::directory::DirectoryConfigurationLoader;
+use crate::configuration::{Configuration, ConfigurationError, ConfigurationLoader};
+use crate::engine::handle::{EngineHandle, Refresh};
+use crate::engine::health::IntegrationHealth;
+use crate::engine::integrations::{Integrations, ModelRegistration, Update};
+use crate::engine::registry::IntegrationRegistry;
+use crate::global_configuration::{GlobalConfiguration, GLOBAL_CONFIGURATION_KEY};
 use crate::integration::Integration;
-use crate::model::ModelManager;
+use crate::metrics::Metrics;
+use crate::model::{Inputs, ModelManager, Policy};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use futures::future::join_all;
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::time::Instant;
+
+const CONFIGURATION_POLL: Duration = Duration::from_secs(15);
 
-#[derive(Default)]
 pub struct Engine {
     state_manager: ModelManager,
     integrations: Integrations,
+    registry: IntegrationRegistry,
+    global_configuration: RwLock<GlobalConfiguration>,
+    configuration: Option<Mutex<DirectoryConfigurationLoader<PathBuf>>>,
+    configured: AtomicBool,
+    configuration_errors: std::sync::Mutex<BTreeMap<String, ConfigurationError>>,
+    strict: bool,
+    shutdown: Arc<watch::Sender<bool>>,
+    refresh: mpsc::UnboundedSender<Refresh>,
+    refresh_requests: Mutex<mpsc::UnboundedReceiver<Refresh>>,
+    clock: SharedClock,
+    metrics: Metrics,
+    metrics_file: Option<PathBuf>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        let (refresh, refresh_requests) = mpsc::unbounded_channel();
+        let metrics = Metrics::default();
+        let mut integrations = Integrations::default();
+        integrations.set_metrics(metrics.clone());
+        Self {
+            state_manager: Default::default(),
+            integrations,
+            registry: Default::default(),
+            global_configuration: Default::default(),
+            configuration: None,
+            configured: AtomicBool::new(false),
+            configuration_errors: Default::default(),
+            strict: false,
+            shutdown: Arc::new(watch::channel(false).0),
+            refresh,
+            refresh_requests: Mutex::new(refresh_requests),
+            clock: Arc::new(SystemClock),
+            metrics,
+            metrics_file: None,
+        }
+    }
 }
 
 impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
     pub fn register<I: Integration>(&mut self, integration: I) {
         self.integrations
-            .register(&mut self.state_manager, integration);
+            .register(&mut self.state_manager, I::info().key, integration);
+    }
+
+    /// Register one of several instances of an integration, e.g. a second
+    /// BirdNet station. The instance is keyed `<key>.<name>`: it reads its
+    /// configuration from `<key>.<name>.toml` and provides its models under
+    /// that key, see [`ModelKey::provided_by`](crate::model::ModelKey::provided_by).
+    pub fn register_instance<I: Integration>(&mut self, name: &str, integration: I) {
+        self.integrations.register(
+            &mut self.state_manager,
+            format!("{}.{}", I::info().key, name),
+            integration,
+        );
+    }
+
+    /// Make the integration available to [`Engine::instantiate`], built by
+    /// `factory` once per configuration file.
+    pub fn register_factory<I, F>(&mut self, factory: F)
+    where
+        I: Integration,
+        F: Fn() -> I + Send + Sync + 'static,
+    {
+        self.registry.register(factory);
+    }
+
+    /// Register an integration for every `<key>.toml` or `<key>.<name>.toml`
+    /// in the configuration directory with a factory for `<key>`, unless one
+    /// is registered under that key already. Returns the keys registered.
+    ///
+    /// Files are only looked for now, so call once before [`Engine::run`].
+    pub fn instantiate(&mut self) -> Vec<String> {
+        let keys = match self.configuration.as_mut() {
+            Some(loader) => loader.get_mut().keys(),
+            None => return vec![],
+        };
+
+        let mut instantiated = Vec::new();
+        for key in keys {
+            if key == GLOBAL_CONFIGURATION_KEY || self.integrations.contains(&key) {
+                continue;
+            }
+            if self
+                .registry
+                .instantiate(&key, &mut self.integrations, &mut self.state_manager)
+            {
+                log::info!("instantiated {}", key);
+                instantiated.push(key);
+            }
+        }
+        instantiated
+    }
+
+    /// Take the current time from `clock`, e.g. a
+    /// [`ManualClock`](crate::clock::ManualClock) in tests. Schedules, model
+    /// timestamps and staleness all follow it. Set it before registering
+    /// integrations, which are handed it through
+    /// [`IntegrationContext::clock`](integrations::IntegrationContext::clock).
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.clock = Arc::new(clock);
+        self.integrations.set_clock(self.clock.clone());
+        self.state_manager.set_clock(self.clock.clone());
+    }
+
+    /// Update counts and timings, to which a coordinator can add its render
+    /// and display timings. Export them with [`Metrics::encode`].
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.clone()
+    }
+
+    /// Write [`Engine::metrics`] to `path` after every round of updates.
+    pub fn set_metrics_file<P: Into<PathBuf>>(&mut self, path: P) {
+        self.metrics_file = Some(path.into());
+    }
+
+    /// Persist models to `path`. Call before registering integrations, so
+    /// their models are restored from the previous run.
+    pub fn set_state_directory<P: Into<PathBuf>>(&mut self, path: P) {
+        self.state_manager.set_state_directory(path);
+    }
+
+    /// Derive `Output` from whatever provides `Input`, including types that
+    /// are themselves derived, e.g. to turn a forecast's temperature series
+    /// into sparkline data.
+    pub fn provides<Input, Output>(&mut self)
+    where
+        Input: Debug + Clone + Sync + Send + 'static,
+        Output: Debug + From<Input> + Send + 'static,
+    {
+        self.state_manager.provides::<Input, Output>();
+    }
+
+    pub fn try_provides<Input, Output>(&mut self)
+    where
+        Input: Debug + Clone + Sync + Send + 'static,
+        Output: Debug + TryFrom<Input> + Send + 'static,
+    {
+        self.state_manager.try_provides::<Input, Output>();
+    }
+
+    /// Register a model computed from several others, see
+    /// [`ModelManager::derive`]. Recomputed after every round of updates.
+    pub fn derive<In, Out, F>(&mut self, derivation: F) -> ModelRegistration<Out>
+    where
+        In: Inputs,
+        Out: Clone + Sync + Send + Debug + PartialEq + 'static,
+        F: Fn(In::Values) -> Option<Out> + Send + Sync + 'static,
+    {
+        let key = self.state_manager.derived_key::<Out>();
+        self.state_manager.derive::<In, Out, F>(derivation);
+        ModelRegistration::new(&mut self.state_manager, &key)
+    }
+
+    /// Register a model computed from several others and the engine's
+    /// clock, see [`ModelManager::derive_with_clock`].
+    pub fn derive_with_clock<In, Out, F>(&mut self, derivation: F) -> ModelRegistration<Out>
+    where
+        In: Inputs,
+        Out: Clone + Sync + Send + Debug + PartialEq + 'static,
+        F: Fn(DateTime<Tz>, In::Values) -> Option<Out> + Send + Sync + 'static,
+    {
+        let key = self.state_manager.derived_key::<Out>();
+        self.state_manager
+            .derive_with_clock::<In, Out, F>(derivation);
+        ModelRegistration::new(&mut self.state_manager, &key)
+    }
+
+    /// Decide which provider pages get when several can supply a `T`.
+    pub fn set_policy<T: 'static>(&mut self, policy: Policy<T>) {
+        self.state_manager.set_policy(policy);
+    }
+
+    /// Read integration configuration from `<key>.toml` files in `path`.
+    pub fn set_configuration_directory<P: Into<PathBuf>>(&mut self, path: P) {
+        self.configuration = Some(Mutex::new(DirectoryConfigurationLoader::new(path.into())));
+    }
+
+    pub fn global_configuration(&self) -> GlobalConfiguration {
+        self.global_configuration.read().unwrap().clone()
+    }
+
+    /// Replace the global configuration. Integrations pick it up on the next
+    /// configuration load; a `global.toml` in the configuration directory
+    /// takes precedence.
+    pub fn set_global_configuration(&mut self, global_configuration: GlobalConfiguration) {
+        self.state_manager
+            .set_timezone(global_configuration.timezone);
+        *self.global_configuration.get_mut().unwrap() = global_configuration;
+    }
+
+    /// In strict mode [`Engine::run`] stops at the first configuration error
+    /// instead of logging it and carrying on with the previous configuration.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Configure integrations from any new or modified configuration files.
+    ///
+    /// The first call configures every integration, passing `None` to those
+    /// without a configuration file; later calls only reconfigure
+    /// integrations whose file changed. An integration whose configuration
+    /// fails to parse keeps its previous configuration.
+    ///
+    /// `global.toml` is reserved for the [`GlobalConfiguration`]; when it
+    /// changes every integration is reconfigured.
+    pub async fn load_configuration(&self) -> Result<(), Vec<ConfigurationError>> {
+        let loaded = if let Some(loader) = &self.configuration {
+            loader.lock().await.load().await
+        } else {
+            Vec::new()
+        };
+        self.apply_configuration(loaded).await
+    }
+
+    /// The second half of [`Engine::load_configuration`], for configurations
+    /// that did not come from the directory.
+    pub(crate) async fn apply_configuration(
+        &self,
+        loaded: Vec<Result<Configuration, ConfigurationError>>,
+    ) -> Result<(), Vec<ConfigurationError>> {
+        let mut configurations = Vec::new();
+        let mut errors = Vec::new();
+        for each in loaded {
+            match each {
+                Ok(configuration) => configurations.push(configuration),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        let loaded_keys = configurations
+            .iter()
+            .map(|inner| inner.key.clone())
+            .collect::<Vec<_>>();
+
+        let mut all = !self.configured.swap(true, Ordering::SeqCst);
+
+        if let Some(index) = configurations
+            .iter()
+            .position(|inner| inner.key == GLOBAL_CONFIGURATION_KEY)
+        {
+            let global_configuration = configurations.swap_remove(index);
+            // a deleted global configuration falls back to the defaults
+            let global_configuration = if global_configuration.removed {
+                Ok(GlobalConfiguration::default())
+            } else {
+                global_configuration.deserialize::<GlobalConfiguration>()
+            };
+            match global_configuration {
+                Ok(global_configuration) => {
+                    log::info!("global configuration: {:?}", global_configuration);
+                    self.state_manager
+                        .set_timezone(global_configuration.timezone);
+                    *self.global_configuration.write().unwrap() = global_configuration;
+                    all = true;
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+
+        if all || !configurations.is_empty() {
+            let global_configuration = self.global_configuration();
+            errors.extend(
+                self.integrations
+                    .configure(&global_configuration, configurations, all)
+                    .await,
+            );
+        }
+
+        let mut current = self.configuration_errors.lock().unwrap();
+        for key in loaded_keys {
+            current.remove(&key);
+        }
+        for err in &errors {
+            log::error!("configuration error: {}", err);
+            current.insert(err.key.clone(), err.clone());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Configuration errors that have not since been fixed, by key.
+    pub fn configuration_errors(&self) -> Vec<ConfigurationError> {
+        self.configuration_errors
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub async fn health(&self) -> Vec<IntegrationHealth> {
+        self.integrations.health().await
+    }
+
+    pub fn model_manager(&self) -> &ModelManager {
+        &self.state_manager
     }
 
-    pub async fn run(&self) {}
+    pub fn handle(&self) -> EngineHandle {
+        EngineHandle::new(self.shutdown.clone(), self.refresh.clone())
+    }
+
+    async fn refresh(&self, refresh: Refresh) {
+        if !self
+            .integrations
+            .refresh(
+                refresh.integration.as_deref(),
+                refresh.controller.as_deref(),
+            )
+            .await
+        {
+            log::warn!(
+                "nothing to refresh for {} {}",
+                refresh.integration.as_deref().unwrap_or("*"),
+                refresh.controller.as_deref().unwrap_or("*")
+            );
+        }
+    }
+
+    /// One round of [`Engine::run`]: apply pending refresh requests, update
+    /// whatever is due as of the clock, recompute derived models and save
+    /// snapshots. Returns the controllers that ran.
+    pub async fn tick(&self) -> Vec<Update> {
+        // while running, the loop applies refresh requests itself
+        if let Ok(mut refresh_requests) = self.refresh_requests.try_lock() {
+            while let Ok(refresh) = refresh_requests.try_recv() {
+                self.refresh(refresh).await;
+            }
+        }
+        let updates = join_all(self.integrations.update().await).await;
+        self.settle().await;
+        // tasks are only aborted by `run`
+        updates.into_iter().filter_map(Result::ok).collect()
+    }
+
+    /// Recompute derived models, save snapshots and write metrics after
+    /// updates completed.
+    async fn settle(&self) {
+        self.state_manager.recompute().await;
+        self.state_manager.save().await;
+
+        if let Some(path) = &self.metrics_file {
+            if let Err(err) = self.metrics.write(path).await {
+                log::warn!("failed to write metrics to {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    /// Drive every registered controller at its cadence until shutdown is
+    /// requested through an [`EngineHandle`].
+    ///
+    /// Rather than polling, the scheduler sleeps until the earliest deadline
+    /// across all controllers, starts whatever is due, and repeats.
+    /// Updates run in their own tasks, so a slow controller holds up neither
+    /// the others nor shutdown and refresh requests. Configuration files are
+    /// re-read every few seconds in between.
+    ///
+    /// Only returns an error in strict mode, once integrations are stopped
+    /// and snapshots saved as on shutdown.
+    pub async fn run(&self) -> Result<(), Vec<ConfigurationError>> {
+        let mut shutdown = self.shutdown.subscribe();
+        let mut refresh_requests = self.refresh_requests.lock().await;
+        let mut next_reload = Instant::now();
+        let mut running = FuturesUnordered::new();
+        let mut result = Ok(());
+
+        while !*shutdown.borrow_and_update() {
+            if Instant::now() >= next_reload {
+                if let Err(errors) = self.load_configuration().await {
+                    if self.strict {
+                        result = Err(errors);
+                        break;
+                    }
+                }
+                next_reload = Instant::now() + CONFIGURATION_POLL;
+            }
+
+            running.extend(self.integrations.update().await);
+            self.settle().await;
+
+            let mut wake = next_reload;
+            if let Some(deadline) = self.integrations.next_update().await {
+                let delay = (deadline - self.clock.now()).to_std().unwrap_or_default();
+                wake = wake.min(Instant::now() + delay);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(wake) => {}
+                _ = shutdown.changed() => {}
+                // the engine holds a sender itself, so this never yields `None`
+                Some(refresh) = refresh_requests.recv() => self.refresh(refresh).await,
+                Some(_) = running.next(), if !running.is_empty() => {}
+            }
+        }
+
+        // updates still in flight are cancelled rather than waited for
+        running.iter().for_each(|task| task.abort());
+        while running.next().await.is_some() {}
+        self.integrations.stop().await;
+        self.state_manager.save().await;
+        result
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use std::future::Future;
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::SystemTime;
 
-    use chrono::Duration;
+    use chrono::{Duration, Utc};
+    use futures::future::join_all;
+    use serde::{Deserialize, Serialize};
 
+    use crate::clock::{Clock, ManualClock};
+    use crate::engine::health::Backoff;
     use crate::engine::integrations::IntegrationContext;
     use crate::engine::Engine;
-    use crate::integration::{Integration, IntegrationInfo};
+    use crate::global_configuration::{GlobalConfiguration, Units};
+    use crate::integration::{Integration, IntegrationInfo, UpdateError};
+    use crate::model::Model;
 
+    #[derive(Default)]
     struct AccuWeather {
-        hourly: HourlyForecast,
+        hourly: Arc<AtomicUsize>,
+        daily: Arc<AtomicUsize>,
     }
 
-    struct HourlyForecast {}
-
-    #[derive(Hash, Eq, PartialEq, Copy, Clone)]
+    #[derive(Hash, Eq, PartialEq, Copy, Clone, Debug)]
     enum AccuWeatherControllers {
         Hourly,
         Daily,
@@ -46,10 +483,10 @@ mod test {
         type Configuration = ();
 
         fn info() -> IntegrationInfo {
-            IntegrationInfo::new("AccuWeather".to_string())
+            IntegrationInfo::new("accuweather", "AccuWeather")
         }
 
-        fn integrate(&self, context: &mut IntegrationContext<Self>)
+        fn integrate(&mut self, context: &mut IntegrationContext<Self>)
         where
             Self: Sized,
         {
@@ -57,30 +494,815 @@ mod test {
             context.register_controller(AccuWeatherControllers::Daily, Duration::minutes(30));
         }
 
-        fn configure(&mut self, controller_config: Option<Self::Configuration>) {
+        async fn configure(
+            &mut self,
+            _global_configuration: GlobalConfiguration,
+            _integration_configuration: Option<Self::Configuration>,
+        ) {
             // nothing
         }
 
-        fn update(
+        async fn update(&mut self, discriminant: Self::Discriminant) -> Result<(), UpdateError> {
+            match discriminant {
+                AccuWeatherControllers::Hourly => self.hourly.fetch_add(1, Ordering::SeqCst),
+                AccuWeatherControllers::Daily => self.daily.fetch_add(1, Ordering::SeqCst),
+            };
+            Ok(())
+        }
+    }
+
+    /// Fails its first `failures` updates, then succeeds.
+    struct Flaky {
+        failures: usize,
+        attempts: Arc<AtomicUsize>,
+    }
+
+    impl Integration for Flaky {
+        type Discriminant = ();
+        type Configuration = ();
+
+        fn info() -> IntegrationInfo {
+            IntegrationInfo::new("flaky", "Flaky")
+        }
+
+        fn integrate(&mut self, context: &mut IntegrationContext<Self>)
+        where
+            Self: Sized,
+        {
+            context
+                .register_controller((), Duration::minutes(5))
+                .backoff(Backoff {
+                    initial: Duration::milliseconds(20),
+                    max: None,
+                    jitter: 0.0,
+                });
+        }
+
+        async fn configure(
             &mut self,
-            discriminant: Self::Discriminant,
-        ) -> impl Future<Output = ()> + Send + Sync {
-            async move {
-                match discriminant {
-                    AccuWeatherControllers::Hourly => {}
-                    AccuWeatherControllers::Daily => {}
-                }
+            _global_configuration: GlobalConfiguration,
+            _integration_configuration: Option<Self::Configuration>,
+        ) {
+        }
+
+        async fn update(&mut self, _discriminant: Self::Discriminant) -> Result<(), UpdateError> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                Err("service unavailable".into())
+            } else {
+                Ok(())
             }
         }
     }
 
     #[tokio::test]
-    async fn whut() {
+    async fn run_until_shutdown() {
+        let integration = AccuWeather::default();
+        let hourly = integration.hourly.clone();
+        let daily = integration.daily.clone();
+
+        let mut engine = Engine::new();
+        engine.register(integration);
+
+        let handle = engine.handle();
+        let (result, _) = tokio::join!(engine.run(), async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            handle.shutdown();
+        });
+        result.unwrap();
+
+        // both controllers are due immediately, then not again for minutes
+        assert_eq!(hourly.load(Ordering::SeqCst), 1);
+        assert_eq!(daily.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn cadence_follows_clock() {
+        let integration = AccuWeather::default();
+        let hourly = integration.hourly.clone();
+        let daily = integration.daily.clone();
+
+        let clock = ManualClock::new("2024-03-01T12:00:00Z".parse().unwrap());
+        let mut engine = Engine::new();
+        engine.set_clock(clock.clone());
+        engine.register(integration);
+
+        engine.tick().await;
+        engine.tick().await;
+        assert_eq!(hourly.load(Ordering::SeqCst), 1);
+        assert_eq!(daily.load(Ordering::SeqCst), 1);
+
+        clock.advance(Duration::minutes(5));
+        engine.tick().await;
+        assert_eq!(hourly.load(Ordering::SeqCst), 2);
+        assert_eq!(daily.load(Ordering::SeqCst), 1);
+
+        clock.advance(Duration::minutes(25));
+        engine.tick().await;
+        assert_eq!(hourly.load(Ordering::SeqCst), 3);
+        assert_eq!(daily.load(Ordering::SeqCst), 2);
+
+        let health = engine.health().await;
+        let controller = &health[0].controllers[0];
+        assert_eq!(controller.health.last_success, Some(clock.now()));
+        assert_eq!(
+            controller.next_update,
+            Some(clock.now() + controller.cadence.unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn metrics_are_written_after_each_tick() {
+        let base = configuration_directory("metrics");
+        let path = base.join("lattitude.prom");
+
+        let mut engine = Engine::new();
+        engine.set_metrics_file(&path);
+        engine.register(AccuWeather::default());
+        engine.register(Flaky {
+            failures: 1,
+            attempts: Default::default(),
+        });
+        engine.tick().await;
+
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(written, engine.metrics().encode());
+        for line in [
+            "lattitude_integration_updates_total{integration=\"accuweather\",controller=\"Daily\"} 1",
+            "lattitude_integration_update_failures_total{integration=\"accuweather\",controller=\"Daily\"} 0",
+            "lattitude_integration_update_failures_total{integration=\"flaky\",controller=\"()\"} 1",
+        ] {
+            assert!(written.lines().any(|inner| inner == line), "missing {}", line);
+        }
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn refresh_on_demand() {
+        let integration = AccuWeather::default();
+        let hourly = integration.hourly.clone();
+        let daily = integration.daily.clone();
+
         let mut engine = Engine::new();
-        engine.register(AccuWeather {
-            hourly: HourlyForecast {},
+        engine.register(integration);
+
+        let handle = engine.handle();
+        let (result, _) = tokio::join!(engine.run(), async {
+            let pause = || tokio::time::sleep(std::time::Duration::from_millis(20));
+            pause().await;
+            handle.refresh("accuweather", Some("Hourly"));
+            pause().await;
+            assert_eq!(hourly.load(Ordering::SeqCst), 2);
+            assert_eq!(daily.load(Ordering::SeqCst), 1);
+
+            handle.refresh_all();
+            pause().await;
+            handle.shutdown();
+        });
+        result.unwrap();
+
+        assert_eq!(hourly.load(Ordering::SeqCst), 3);
+        assert_eq!(daily.load(Ordering::SeqCst), 2);
+
+        // the schedule starts over from the refresh
+        let health = engine.health().await;
+        assert!(health[0]
+            .controllers
+            .iter()
+            .all(|inner| inner.next_update > Some(Utc::now() + Duration::minutes(4))));
+    }
+
+    #[tokio::test]
+    async fn refresh_during_update_is_kept() {
+        let integration = AccuWeather::default();
+        let hourly = integration.hourly.clone();
+
+        let mut engine = Engine::new();
+        engine.register(integration);
+
+        let updates = engine.integrations.update().await;
+        assert_eq!(updates.len(), 2);
+        assert!(
+            engine
+                .integrations
+                .refresh(Some("accuweather"), Some("Hourly"))
+                .await
+        );
+        join_all(updates).await;
+
+        // the refresh arrived after the update was picked, so it runs again
+        join_all(engine.integrations.update().await).await;
+        assert_eq!(hourly.load(Ordering::SeqCst), 2);
+        assert!(engine.integrations.update().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_updates_retry_with_backoff() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let mut engine = Engine::new();
+        engine.register(Flaky {
+            failures: 2,
+            attempts: attempts.clone(),
+        });
+
+        let handle = engine.handle();
+        let (result, _) = tokio::join!(engine.run(), async {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            let health = engine.health().await;
+            assert_eq!(health[0].controllers[0].health.consecutive_failures, 1);
+            assert!(!health[0].is_healthy());
+
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            handle.shutdown();
         });
+        result.unwrap();
+
+        // two failures retried after 20ms and 40ms, then back to the 5 minute cadence
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let health = engine.health().await;
+        let controller = &health[0].controllers[0];
+        assert!(controller.health.is_healthy());
+        assert!(controller.health.last_success.is_some());
+        assert_eq!(
+            controller.health.last_error.as_deref(),
+            Some("service unavailable")
+        );
+    }
+
+    struct Hung;
+
+    impl Integration for Hung {
+        type Discriminant = ();
+        type Configuration = RecorderConfiguration;
 
-        engine.run().await;
+        fn info() -> IntegrationInfo {
+            IntegrationInfo::new("hung", "Hung")
+        }
+
+        fn integrate(&mut self, context: &mut IntegrationContext<Self>)
+        where
+            Self: Sized,
+        {
+            context
+                .register_controller((), Duration::minutes(5))
+                .timeout(Duration::milliseconds(50));
+        }
+
+        async fn configure(
+            &mut self,
+            _global_configuration: GlobalConfiguration,
+            _integration_configuration: Option<Self::Configuration>,
+        ) {
+        }
+
+        async fn update(&mut self, _discriminant: Self::Discriminant) -> Result<(), UpdateError> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn hung_update_times_out_without_stalling_others() {
+        let integration = AccuWeather::default();
+        let hourly = integration.hourly.clone();
+
+        let mut engine = Engine::new();
+        engine.register(Hung);
+        engine.register(integration);
+
+        let updates = async { join_all(engine.integrations.update().await).await };
+        let (_, health) = tokio::join!(updates, async {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            assert_eq!(hourly.load(Ordering::SeqCst), 1);
+            // health stays readable while the update hangs
+            engine.health().await
+        });
+        assert_eq!(health[0].controllers[0].health.last_failure, None);
+
+        let health = engine.health().await;
+        assert_eq!(
+            health[0].controllers[0].health.last_error.as_deref(),
+            Some("timed out after 50ms")
+        );
+        assert!(health[1].is_healthy());
+    }
+
+    /// Hangs on its first start and panics on its second.
+    #[derive(Default)]
+    struct Unstartable {
+        starts: usize,
+    }
+
+    impl Integration for Unstartable {
+        type Discriminant = ();
+        type Configuration = RecorderConfiguration;
+
+        fn info() -> IntegrationInfo {
+            IntegrationInfo::new("unstartable", "Unstartable")
+        }
+
+        fn integrate(&mut self, context: &mut IntegrationContext<Self>)
+        where
+            Self: Sized,
+        {
+            context
+                .register_controller((), Duration::minutes(5))
+                .timeout(Duration::milliseconds(50));
+        }
+
+        async fn configure(
+            &mut self,
+            _global_configuration: GlobalConfiguration,
+            _integration_configuration: Option<Self::Configuration>,
+        ) {
+        }
+
+        async fn update(&mut self, _discriminant: Self::Discriminant) -> Result<(), UpdateError> {
+            Ok(())
+        }
+
+        async fn start(&mut self) -> Result<(), UpdateError> {
+            self.starts += 1;
+            match self.starts {
+                1 => std::future::pending().await,
+                2 => panic!("cannot start"),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn hung_or_panicking_start_fails_the_update() {
+        let clock = ManualClock::new("2024-03-01T12:00:00Z".parse().unwrap());
+        let mut engine = Engine::new();
+        engine.set_clock(clock.clone());
+        engine.register(Unstartable::default());
+
+        let updates = engine.tick().await;
+        assert_eq!(updates[0].error.as_deref(), Some("timed out after 50ms"));
+
+        clock.advance(Duration::hours(1));
+        let updates = engine.tick().await;
+        assert_eq!(updates[0].error.as_deref(), Some("panicked"));
+
+        // still not started, so the next update tries again
+        clock.advance(Duration::hours(1));
+        let updates = engine.tick().await;
+        assert_eq!(updates[0].error, None);
+    }
+
+    #[tokio::test]
+    async fn impossible_cron_backs_off_without_a_cadence() {
+        let base = configuration_directory("impossible");
+        write_configuration(
+            &base.join("hung.toml"),
+            "keep = 1\n[schedule.default]\ncron = \"0 0 30 2 *\"",
+            0,
+        );
+
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&base);
+        engine.register(Hung);
+        engine.load_configuration().await.unwrap();
+
+        // due once, as never attempted, then times out
+        let updates = engine.tick().await;
+        assert!(updates[0].error.is_some());
+
+        let controller = &engine.health().await[0].controllers[0];
+        assert_eq!(controller.cadence, None);
+        assert!(controller.next_update.is_some());
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn hung_update_does_not_hold_up_shutdown() {
+        let base = configuration_directory("timeout");
+        write_configuration(
+            &base.join("hung.toml"),
+            "keep = 1\n[schedule.default]\ntimeout = \"1h\"",
+            0,
+        );
+
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&base);
+        engine.register(Hung);
+
+        let handle = engine.handle();
+        let run = tokio::time::timeout(std::time::Duration::from_secs(5), engine.run());
+        let (result, _) = tokio::join!(run, async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            handle.refresh_all();
+            handle.shutdown();
+        });
+        result.expect("run stalled behind the update").unwrap();
+
+        // the configured timeout replaced the registered 50ms one
+        let health = engine.health().await;
+        assert_eq!(health[0].controllers[0].health.last_failure, None);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct RecorderConfiguration {
+        keep: usize,
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        configured: Arc<std::sync::Mutex<Vec<Option<usize>>>>,
+        worlds: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl Integration for Recorder {
+        type Discriminant = ();
+        type Configuration = RecorderConfiguration;
+
+        fn info() -> IntegrationInfo {
+            IntegrationInfo::new("recorder", "Recorder")
+        }
+
+        fn integrate(&mut self, context: &mut IntegrationContext<Self>)
+        where
+            Self: Sized,
+        {
+            context.register_controller((), Duration::minutes(5));
+        }
+
+        async fn configure(
+            &mut self,
+            global_configuration: GlobalConfiguration,
+            integration_configuration: Option<Self::Configuration>,
+        ) {
+            self.worlds.lock().unwrap().push(global_configuration.name);
+            self.configured
+                .lock()
+                .unwrap()
+                .push(integration_configuration.map(|inner| inner.keep));
+        }
+
+        async fn update(&mut self, _discriminant: Self::Discriminant) -> Result<(), UpdateError> {
+            Ok(())
+        }
+    }
+
+    /// Write `contents` and push the mtime forward so the loader notices
+    /// the change even within the filesystem's timestamp granularity.
+    fn write_configuration(path: &Path, contents: &str, generation: u64) {
+        fs::write(path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(generation))
+            .unwrap();
+    }
+
+    fn configuration_directory(name: &str) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("lattitude-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+        base
+    }
+
+    #[tokio::test]
+    async fn reconfigure_on_change() {
+        let base = configuration_directory("reconfigure");
+        let path = base.join("recorder.toml");
+        write_configuration(&path, "keep = 10", 0);
+
+        let configured = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&base);
+        engine.register(Recorder {
+            configured: configured.clone(),
+            ..Default::default()
+        });
+        engine.register(AccuWeather::default());
+
+        engine.load_configuration().await.unwrap();
+        assert_eq!(*configured.lock().unwrap(), vec![Some(10)]);
+
+        engine.load_configuration().await.unwrap();
+        assert_eq!(configured.lock().unwrap().len(), 1);
+
+        write_configuration(&path, "keep = 20", 5);
+
+        engine.load_configuration().await.unwrap();
+        assert_eq!(*configured.lock().unwrap(), vec![Some(10), Some(20)]);
+
+        // a deleted file leaves the integration without a configuration
+        fs::remove_file(&path).unwrap();
+        engine.load_configuration().await.unwrap();
+        assert_eq!(*configured.lock().unwrap(), vec![Some(10), Some(20), None]);
+
+        engine.load_configuration().await.unwrap();
+        assert_eq!(configured.lock().unwrap().len(), 3);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn invalid_configuration_is_reported() {
+        let base = configuration_directory("invalid");
+        let path = base.join("recorder.toml");
+        write_configuration(&path, "keep = 10", 0);
+
+        let configured = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&base);
+        engine.register(Recorder {
+            configured: configured.clone(),
+            ..Default::default()
+        });
+
+        engine.load_configuration().await.unwrap();
+
+        write_configuration(&path, "keep = \"ten\"", 5);
+        let errors = engine.load_configuration().await.unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path.as_deref(), Some(path.as_path()));
+        assert_eq!(errors[0].field.as_deref(), Some("keep"));
+        assert_eq!(engine.configuration_errors(), errors);
+
+        // the previous configuration stays in effect
+        assert_eq!(*configured.lock().unwrap(), vec![Some(10)]);
+
+        write_configuration(&path, "keep = 12", 10);
+        engine.load_configuration().await.unwrap();
+        assert!(engine.configuration_errors().is_empty());
+        assert_eq!(*configured.lock().unwrap(), vec![Some(10), Some(12)]);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn strict_run_stops_on_invalid_configuration() {
+        let base = configuration_directory("strict");
+        write_configuration(&base.join("recorder.toml"), "keep = -1", 0);
+
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&base);
+        engine.set_strict(true);
+        engine.register(Recorder::default());
+        let connected = Connected::default();
+        let events = connected.events.clone();
+        engine.register(connected);
+        engine.tick().await;
+
+        let errors = engine.run().await.unwrap_err();
+        assert_eq!(errors[0].key, "recorder");
+        // integrations are stopped on the way out, as on shutdown
+        assert_eq!(*events.lock().unwrap(), vec!["start", "update", "stop"]);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn global_configuration_reconfigures_everything() {
+        let base = configuration_directory("global");
+        let global = base.join("global.toml");
+        write_configuration(&global, "name = \"Kitchen\"", 0);
+        write_configuration(&base.join("recorder.toml"), "keep = 10", 0);
+
+        let recorder = Recorder::default();
+        let configured = recorder.configured.clone();
+        let worlds = recorder.worlds.clone();
+
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&base);
+        engine.register(recorder);
+
+        engine.load_configuration().await.unwrap();
+        assert_eq!(engine.global_configuration().name, "Kitchen");
+        assert_eq!(*worlds.lock().unwrap(), vec!["Kitchen"]);
+
+        write_configuration(&global, "name = \"Hallway\"\nunits = \"imperial\"", 5);
+        engine.load_configuration().await.unwrap();
+        assert_eq!(engine.global_configuration().units, Units::Imperial);
+        assert_eq!(*worlds.lock().unwrap(), vec!["Kitchen", "Hallway"]);
+
+        // the integration keeps its own configuration across the change
+        assert_eq!(*configured.lock().unwrap(), vec![Some(10), Some(10)]);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[derive(Default)]
+    struct Connected {
+        events: Arc<std::sync::Mutex<Vec<&'static str>>>,
+        model: Model<u32>,
+    }
+
+    impl Integration for Connected {
+        type Discriminant = ();
+        type Configuration = RecorderConfiguration;
+
+        fn info() -> IntegrationInfo {
+            IntegrationInfo::new("connected", "Connected")
+        }
+
+        fn integrate(&mut self, context: &mut IntegrationContext<Self>)
+        where
+            Self: Sized,
+        {
+            context.register_controller((), Duration::minutes(5));
+            context.register_model(self.model.clone());
+        }
+
+        async fn configure(
+            &mut self,
+            _global_configuration: GlobalConfiguration,
+            _integration_configuration: Option<Self::Configuration>,
+        ) {
+        }
+
+        async fn update(&mut self, _discriminant: Self::Discriminant) -> Result<(), UpdateError> {
+            self.events.lock().unwrap().push("update");
+            self.model.update(1).await;
+            Ok(())
+        }
+
+        async fn start(&mut self) -> Result<(), UpdateError> {
+            self.events.lock().unwrap().push("start");
+            Ok(())
+        }
+
+        async fn stop(&mut self) {
+            self.events.lock().unwrap().push("stop");
+        }
+    }
+
+    #[tokio::test]
+    async fn disable_through_configuration() {
+        let base = configuration_directory("lifecycle");
+        let path = base.join("connected.toml");
+        write_configuration(&path, "keep = 1", 0);
+
+        let integration = Connected::default();
+        let events = integration.events.clone();
+        let model = integration.model.clone();
+
+        let clock = ManualClock::new("2024-03-01T12:00:00Z".parse().unwrap());
+        let mut engine = Engine::new();
+        engine.set_clock(clock.clone());
+        engine.set_configuration_directory(&base);
+        engine.register(integration);
+
+        engine.load_configuration().await.unwrap();
+        engine.tick().await;
+        assert_eq!(*events.lock().unwrap(), vec!["start", "update"]);
+
+        write_configuration(
+            &path,
+            "keep = 1\nenabled = false\nwhen_disabled = \"clear\"",
+            5,
+        );
+        engine.load_configuration().await.unwrap();
+        assert_eq!(*events.lock().unwrap(), vec!["start", "update", "stop"]);
+        assert_eq!(model.get().await, None);
+
+        // suspended, however long it waits
+        clock.advance(Duration::hours(1));
+        engine.tick().await;
+        assert_eq!(events.lock().unwrap().len(), 3);
+        assert!(!engine.health().await[0].enabled);
+
+        write_configuration(&path, "keep = 1", 10);
+        engine.load_configuration().await.unwrap();
+        engine.tick().await;
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["start", "update", "stop", "start", "update"]
+        );
+        assert_eq!(model.get().await, Some(1));
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_integrations() {
+        let integration = Connected::default();
+        let events = integration.events.clone();
+
+        let mut engine = Engine::new();
+        engine.register(integration);
+
+        let handle = engine.handle();
+        let (result, _) = tokio::join!(engine.run(), async move {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            handle.shutdown();
+        });
+        result.unwrap();
+
+        assert_eq!(*events.lock().unwrap(), vec!["start", "update", "stop"]);
+    }
+
+    #[tokio::test]
+    async fn configuration_overrides_schedule() {
+        let base = configuration_directory("schedule");
+        let path = base.join("recorder.toml");
+        write_configuration(
+            &path,
+            "keep = 1\n[schedule.default]\ncron = \"0 6 * * *\"",
+            0,
+        );
+
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&base);
+        engine.register(Recorder::default());
+
+        engine.load_configuration().await.unwrap();
+        let health = engine.health().await;
+        assert_eq!(health[0].controllers[0].cadence, Some(Duration::days(1)));
+
+        write_configuration(&path, "keep = 1\n[schedule.Hourly]\nevery = \"1h\"", 5);
+        let errors = engine.load_configuration().await.unwrap_err();
+        assert_eq!(errors[0].field.as_deref(), Some("schedule.Hourly"));
+
+        // without an override the registered schedule applies again
+        write_configuration(&path, "keep = 1", 10);
+        engine.load_configuration().await.unwrap();
+        let health = engine.health().await;
+        assert_eq!(health[0].controllers[0].cadence, Some(Duration::minutes(5)));
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn instantiate_configured_integrations() {
+        let base = configuration_directory("registry");
+        write_configuration(&base.join("recorder.toml"), "keep = 1", 0);
+        write_configuration(&base.join("recorder.cabin.toml"), "keep = 2", 0);
+        write_configuration(&base.join("global.toml"), "name = \"Kitchen\"", 0);
+        write_configuration(&base.join("unknown.toml"), "", 0);
+
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&base);
+        engine.register_factory(Recorder::default);
+        engine.register_factory(AccuWeather::default);
+
+        assert_eq!(engine.instantiate(), vec!["recorder", "recorder.cabin"]);
+        assert!(engine.instantiate().is_empty());
+
+        engine.load_configuration().await.unwrap();
+        let keys: Vec<_> = engine
+            .health()
+            .await
+            .into_iter()
+            .map(|inner| inner.key)
+            .collect();
+        assert_eq!(keys, vec!["recorder", "recorder.cabin"]);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[tokio::test]
+    async fn instances_read_their_own_configuration() {
+        let base = configuration_directory("instances");
+        write_configuration(&base.join("recorder.backyard.toml"), "keep = 1", 0);
+        write_configuration(&base.join("recorder.cabin.toml"), "keep = 2", 0);
+
+        let backyard = Recorder::default();
+        let cabin = Recorder::default();
+        let backyard_configured = backyard.configured.clone();
+        let cabin_configured = cabin.configured.clone();
+
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&base);
+        engine.register_instance("backyard", backyard);
+        engine.register_instance("cabin", cabin);
+
+        engine.load_configuration().await.unwrap();
+        assert_eq!(*backyard_configured.lock().unwrap(), vec![Some(1)]);
+        assert_eq!(*cabin_configured.lock().unwrap(), vec![Some(2)]);
+
+        let keys: Vec<_> = engine
+            .health()
+            .await
+            .into_iter()
+            .map(|inner| inner.key)
+            .collect();
+        assert_eq!(keys, vec!["recorder.backyard", "recorder.cabin"]);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered")]
+    fn duplicate_instances_are_rejected() {
+        let mut engine = Engine::new();
+        engine.register_instance("backyard", Recorder::default());
+        engine.register_instance("backyard", Recorder::default());
+    }
+
+    #[tokio::test]
+    async fn run_without_integrations() {
+        let engine = Engine::new();
+        let handle = engine.handle();
+        handle.shutdown();
+        engine.run().await.unwrap();
     }
 }
@@ -0,0 +1,51 @@
+use crate::engine::integrations::Integrations;
+use crate::integration::Integration;
+use crate::model::ModelManager;
+use std::collections::BTreeMap;
+
+type Factory = Box<dyn Fn(&mut Integrations, &mut ModelManager, String) + Send + Sync>;
+
+/// Integrations the engine knows how to build, by [`IntegrationInfo::key`](crate::integration::IntegrationInfo::key).
+#[derive(Default)]
+pub struct IntegrationRegistry {
+    factories: BTreeMap<String, Factory>,
+}
+
+impl IntegrationRegistry {
+    pub fn register<I, F>(&mut self, factory: F)
+    where
+        I: Integration,
+        F: Fn() -> I + Send + Sync + 'static,
+    {
+        self.factories.insert(
+            I::info().key,
+            Box::new(move |integrations, model_manager, key| {
+                integrations.register(model_manager, key, factory())
+            }),
+        );
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+
+    /// Build and register the integration for the instance `key`, either
+    /// `<key>` or `<key>.<name>`. Returns whether a factory matched.
+    pub(crate) fn instantiate(
+        &self,
+        key: &str,
+        integrations: &mut Integrations,
+        model_manager: &mut ModelManager,
+    ) -> bool {
+        let integration = key
+            .split_once('.')
+            .map_or(key, |(integration, _)| integration);
+        match self.factories.get(integration) {
+            Some(factory) => {
+                factory(integrations, model_manager, key.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+}
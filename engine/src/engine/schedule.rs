@@ -0,0 +1,567 @@
+use crate::configuration::{Configuration, ConfigurationError};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Configuration table holding per-controller schedule overrides.
+pub const SCHEDULE_KEY: &str = "schedule";
+
+/// Overrides under this name apply to every controller without its own.
+pub const DEFAULT_CONTROLLER: &str = "default";
+
+/// When a controller is due.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule {
+    /// A fixed interval after the previous attempt.
+    Every(Duration),
+    /// Wall-clock instants in the configured timezone.
+    Cron(Cron),
+}
+
+impl Schedule {
+    pub fn cron(expression: &str) -> Result<Self, ScheduleError> {
+        Ok(Self::Cron(expression.parse()?))
+    }
+
+    /// The first instant after `last` at which the controller is due, or
+    /// `None` for a cron that never matches, e.g. `0 0 30 2 *`.
+    pub(crate) fn next_after(&self, last: DateTime<Utc>, timezone: Tz) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(cadence) => Some(last + *cadence),
+            Schedule::Cron(cron) => cron.next_after(last, timezone),
+        }
+    }
+
+    /// The gap between two consecutive updates from `now`, which caps
+    /// the backoff of a failing controller. `None` unless the schedule
+    /// matches twice more.
+    pub(crate) fn period(&self, now: DateTime<Utc>, timezone: Tz) -> Option<Duration> {
+        match self {
+            Schedule::Every(cadence) => Some(*cadence),
+            Schedule::Cron(cron) => {
+                let next = cron.next_after(now, timezone)?;
+                Some(cron.next_after(next, timezone)? - next)
+            }
+        }
+    }
+}
+
+impl From<Duration> for Schedule {
+    fn from(cadence: Duration) -> Self {
+        Self::Every(cadence)
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Every(cadence) => write!(f, "every {}s", cadence.num_seconds()),
+            Schedule::Cron(cron) => write!(f, "{}", cron.expression),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduleError {
+    pub message: String,
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+fn error<T>(message: String) -> Result<T, ScheduleError> {
+    Err(ScheduleError { message })
+}
+
+/// A five-field cron expression: minute, hour, day of month, month and day
+/// of week, e.g. `2 * * * *` for two minutes past every hour or `0 6 * * *`
+/// for 06:00 daily. Fields accept `*`, numbers, ranges, lists and steps.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Cron {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Cron {
+    /// The first whole minute after `after` that matches, looking up to
+    /// five years ahead. Local times skipped by a DST change never match.
+    pub fn next_after(&self, after: DateTime<Utc>, timezone: Tz) -> Option<DateTime<Utc>> {
+        let local = after.with_timezone(&timezone).naive_local();
+        let mut candidate =
+            local.date().and_hms_opt(local.hour(), local.minute(), 0)? + Duration::minutes(1);
+        let limit = candidate + Duration::days(5 * 366);
+
+        while candidate <= limit {
+            if !bit(self.months, candidate.month()) {
+                candidate = next_month(candidate)?;
+            } else if !self.day_matches(candidate) {
+                candidate = candidate.date().succ_opt()?.and_time(NaiveTime::MIN);
+            } else if !bit(self.hours, candidate.hour()) {
+                candidate =
+                    candidate.date().and_hms_opt(candidate.hour(), 0, 0)? + Duration::hours(1);
+            } else if !bit(self.minutes, candidate.minute()) {
+                candidate += Duration::minutes(1);
+            } else {
+                if let Some(at) = timezone.from_local_datetime(&candidate).earliest() {
+                    let at = at.with_timezone(&Utc);
+                    if at > after {
+                        return Some(at);
+                    }
+                }
+                candidate += Duration::minutes(1);
+            }
+        }
+
+        None
+    }
+
+    /// As in cron(8), a restricted day of month and day of week match if
+    /// either does.
+    fn day_matches(&self, candidate: NaiveDateTime) -> bool {
+        let day = bit(self.days, candidate.day());
+        let weekday = bit(self.weekdays, candidate.weekday().num_days_from_sunday());
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn bit(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn next_month(candidate: NaiveDateTime) -> Option<NaiveDateTime> {
+    let (year, month) = match candidate.month() {
+        12 => (candidate.year() + 1, 1),
+        month => (candidate.year(), month + 1),
+    };
+    Some(chrono::NaiveDate::from_ymd_opt(year, month, 1)?.and_time(NaiveTime::MIN))
+}
+
+/// Parse one field into a bit set over `min..=max`.
+fn field(source: &str, name: &str, min: u32, max: u32) -> Result<(u64, bool), ScheduleError> {
+    let mut set = 0;
+    let mut restricted = false;
+
+    for part in source.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return error(format!("invalid step in {} field: {}", name, part)),
+            },
+            None => (part, 1),
+        };
+
+        let number = |value: &str| match value.parse::<u32>() {
+            Ok(value) if (min..=max).contains(&value) => Ok(value),
+            _ => error(format!(
+                "{} field expects {} to {}, got {}",
+                name, min, max, value
+            )),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (number(start)?, number(end)?)
+        } else if step > 1 {
+            // `5/15` runs from 5 to the end of the range
+            (number(range)?, max)
+        } else {
+            let value = number(range)?;
+            (value, value)
+        };
+
+        if start > end {
+            return error(format!("empty range in {} field: {}", name, part));
+        }
+        restricted |= range != "*" || step > 1;
+
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok((set, restricted))
+}
+
+impl FromStr for Cron {
+    type Err = ScheduleError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return error(format!(
+                "expected 5 fields in cron expression, got {}",
+                fields.len()
+            ));
+        };
+
+        let (minutes, _) = field(minutes, "minute", 0, 59)?;
+        let (hours, _) = field(hours, "hour", 0, 23)?;
+        let (days, days_restricted) = field(days, "day of month", 1, 31)?;
+        let (months, _) = field(months, "month", 1, 12)?;
+        let (mut weekdays, weekdays_restricted) = field(weekdays, "day of week", 0, 7)?;
+        // both 0 and 7 are Sunday
+        if bit(weekdays, 7) {
+            weekdays |= 1;
+        }
+
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes,
+            hours,
+            days,
+            months,
+            weekdays,
+            days_restricted,
+            weekdays_restricted,
+        })
+    }
+}
+
+impl TryFrom<String> for Cron {
+    type Error = ScheduleError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// A daily window in the configured timezone during which a controller
+/// pauses, e.g. from 22:00 until 05:30. Updates due within the window are
+/// deferred to its end.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuietHours {
+    pub from: NaiveTime,
+    pub until: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn new(from: NaiveTime, until: NaiveTime) -> Self {
+        Self { from, until }
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.until {
+            self.from <= time && time < self.until
+        } else {
+            // spans midnight
+            time >= self.from || time < self.until
+        }
+    }
+
+    /// `at`, or the end of the window if `at` falls within it.
+    pub(crate) fn defer(&self, at: DateTime<Utc>, timezone: Tz) -> DateTime<Utc> {
+        let local = at.with_timezone(&timezone);
+        if !self.contains(local.time()) {
+            return at;
+        }
+
+        let mut date = local.date_naive();
+        if local.time() >= self.until {
+            // the window spans midnight and we are before it
+            date = date.succ_opt().unwrap_or(date);
+        }
+        let end = date.and_time(self.until);
+
+        timezone
+            .from_local_datetime(&end)
+            .earliest()
+            // the end fell into a DST gap
+            .or_else(|| {
+                timezone
+                    .from_local_datetime(&(end + Duration::hours(1)))
+                    .earliest()
+            })
+            .map_or(at, |end| end.with_timezone(&Utc))
+    }
+}
+
+/// Interval of an `every` or `timeout` override, e.g. `90s`, `10m` or
+/// `1h30m`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct Every(Duration);
+
+impl TryFrom<String> for Every {
+    type Error = ScheduleError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let mut total = Duration::zero();
+        let mut digits = String::new();
+
+        for c in value.trim().chars() {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                continue;
+            }
+            let Ok(amount) = digits.parse::<i64>() else {
+                return error(format!("invalid interval: {}", value));
+            };
+            total += match c {
+                's' => Duration::seconds(amount),
+                'm' => Duration::minutes(amount),
+                'h' => Duration::hours(amount),
+                'd' => Duration::days(amount),
+                _ => return error(format!("invalid interval: {}", value)),
+            };
+            digits.clear();
+        }
+
+        if !digits.is_empty() || total <= Duration::zero() {
+            return error(format!("invalid interval: {}", value));
+        }
+        Ok(Self(total))
+    }
+}
+
+/// A controller's schedule as overridden by the integration's
+/// configuration, e.g.
+///
+/// ```toml
+/// [schedule.RecentDetections]
+/// every = "10m"
+/// quiet_hours = { from = "22:00", until = "05:30" }
+/// timeout = "2m"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ScheduleOverride {
+    every: Option<Every>,
+    cron: Option<Cron>,
+    pub(crate) quiet_hours: Option<QuietHours>,
+    timeout: Option<Every>,
+}
+
+impl ScheduleOverride {
+    /// How long an update may take before it is cancelled.
+    pub(crate) fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(|inner| inner.0)
+    }
+
+    pub(crate) fn schedule(&self) -> Option<Schedule> {
+        match (&self.every, &self.cron) {
+            (Some(every), _) => Some(Schedule::Every(every.0)),
+            (_, Some(cron)) => Some(Schedule::Cron(cron.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Overrides {
+    #[serde(default)]
+    schedule: HashMap<String, ScheduleOverride>,
+}
+
+/// Split the schedule overrides off an integration's configuration, leaving
+/// the rest for the integration itself.
+pub(crate) fn split_overrides(
+    configuration: &Configuration,
+    controllers: &[String],
+) -> Result<(Configuration, HashMap<String, ScheduleOverride>), ConfigurationError> {
+    let overrides = configuration.deserialize::<Overrides>()?.schedule;
+
+    for (controller, inner) in &overrides {
+        let message = if controller != DEFAULT_CONTROLLER && !controllers.contains(controller) {
+            format!(
+                "no such controller, expected one of {}",
+                controllers.join(", ")
+            )
+        } else if inner.every.is_some() && inner.cron.is_some() {
+            "set either every or cron, not both".to_string()
+        } else {
+            continue;
+        };
+        return Err(ConfigurationError {
+            key: configuration.key.clone(),
+            path: configuration.path.clone(),
+            field: Some(format!("{}.{}", SCHEDULE_KEY, controller)),
+            message,
+        });
+    }
+
+    let mut remainder = configuration.clone();
+    if let Some(table) = remainder.content.as_table_mut() {
+        table.remove(SCHEDULE_KEY);
+    }
+    Ok((remainder, overrides))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use toml::toml;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_cron() {
+        assert!("2 * * * *".parse::<Cron>().is_ok());
+        assert!("*/15 6-22 * * 1-5".parse::<Cron>().is_ok());
+        assert!("0 6 1,15 * 7".parse::<Cron>().is_ok());
+
+        assert!("2 * * *".parse::<Cron>().is_err());
+        assert!("60 * * * *".parse::<Cron>().is_err());
+        assert!("*/0 * * * *".parse::<Cron>().is_err());
+        assert!("5-1 * * * *".parse::<Cron>().is_err());
+    }
+
+    #[test]
+    fn cron_follows_wall_clock() {
+        let hourly: Cron = "2 * * * *".parse().unwrap();
+        assert_eq!(
+            hourly.next_after(utc("2024-03-01T10:01:30Z"), Tz::UTC),
+            Some(utc("2024-03-01T10:02:00Z"))
+        );
+        assert_eq!(
+            hourly.next_after(utc("2024-03-01T10:02:00Z"), Tz::UTC),
+            Some(utc("2024-03-01T11:02:00Z"))
+        );
+
+        // 06:00 in New York, across the change to daylight saving time
+        let daily: Cron = "0 6 * * *".parse().unwrap();
+        let new_york = Tz::America__New_York;
+        assert_eq!(
+            daily.next_after(utc("2024-03-09T12:00:00Z"), new_york),
+            Some(utc("2024-03-10T10:00:00Z"))
+        );
+        assert_eq!(
+            daily.next_after(utc("2024-03-10T10:00:00Z"), new_york),
+            Some(utc("2024-03-11T10:00:00Z"))
+        );
+
+        // Mondays, or the first of the month
+        let either: Cron = "0 0 1 * 1".parse().unwrap();
+        assert_eq!(
+            either.next_after(utc("2024-03-26T00:00:00Z"), Tz::UTC),
+            Some(utc("2024-04-01T00:00:00Z"))
+        );
+        assert_eq!(
+            either.next_after(utc("2024-04-01T00:00:00Z"), Tz::UTC),
+            Some(utc("2024-04-08T00:00:00Z"))
+        );
+
+        let never: Cron = "0 0 31 2 *".parse().unwrap();
+        assert_eq!(never.next_after(utc("2024-01-01T00:00:00Z"), Tz::UTC), None);
+    }
+
+    #[test]
+    fn impossible_dates_are_never_due() {
+        let never = Schedule::cron("0 0 30 2 *").unwrap();
+        let now = utc("2024-01-01T00:00:00Z");
+        assert_eq!(never.next_after(now, Tz::UTC), None);
+        assert_eq!(never.period(now, Tz::UTC), None);
+
+        let hourly = Schedule::from(Duration::hours(1));
+        assert_eq!(hourly.period(now, Tz::UTC), Some(Duration::hours(1)));
+    }
+
+    #[test]
+    fn quiet_hours_defer() {
+        let night = QuietHours::new(
+            NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(5, 30, 0).unwrap(),
+        );
+        let berlin = Tz::Europe__Berlin;
+
+        let day = utc("2024-06-01T12:00:00Z");
+        assert_eq!(night.defer(day, berlin), day);
+
+        // 23:00 and 02:00 in Berlin both wait for 05:30
+        assert_eq!(
+            night.defer(utc("2024-06-01T21:00:00Z"), berlin),
+            utc("2024-06-02T03:30:00Z")
+        );
+        assert_eq!(
+            night.defer(utc("2024-06-02T00:00:00Z"), berlin),
+            utc("2024-06-02T03:30:00Z")
+        );
+    }
+
+    #[test]
+    fn overrides_are_split_off() {
+        let configuration = Configuration::new(
+            "birdnet",
+            toml! {
+                token = "secret"
+
+                [schedule.RecentDetections]
+                every = "1h30m"
+                quiet_hours = { from = "22:00", until = "05:30" }
+
+                [schedule.default]
+                cron = "*/10 * * * *"
+                timeout = "2m"
+            }
+            .into(),
+        );
+        let controllers = vec!["RecentDetections".to_string()];
+
+        let (remainder, overrides) = split_overrides(&configuration, &controllers).unwrap();
+        assert!(remainder.content.get(SCHEDULE_KEY).is_none());
+        assert_eq!(remainder.content["token"].as_str(), Some("secret"));
+
+        let recent = &overrides["RecentDetections"];
+        assert_eq!(
+            recent.schedule(),
+            Some(Schedule::Every(Duration::minutes(90)))
+        );
+        assert_eq!(
+            recent.quiet_hours.unwrap().from,
+            NaiveTime::from_hms_opt(22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            overrides[DEFAULT_CONTROLLER].schedule(),
+            Some(Schedule::cron("*/10 * * * *").unwrap())
+        );
+        assert_eq!(recent.timeout(), None);
+        assert_eq!(
+            overrides[DEFAULT_CONTROLLER].timeout(),
+            Some(Duration::minutes(2))
+        );
+
+        let unknown = Configuration::new(
+            "birdnet",
+            toml! {
+                [schedule.Hourly]
+                every = "5m"
+            }
+            .into(),
+        );
+        let err = split_overrides(&unknown, &controllers).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("schedule.Hourly"));
+
+        let invalid = Configuration::new(
+            "birdnet",
+            toml! {
+                [schedule.RecentDetections]
+                cron = "every night"
+            }
+            .into(),
+        );
+        let err = split_overrides(&invalid, &controllers).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("schedule.RecentDetections.cron"));
+    }
+}
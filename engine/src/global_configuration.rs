@@ -1,5 +1,82 @@
-#[derive(Clone)]
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Configuration key of the file holding the [`GlobalConfiguration`],
+/// i.e. `global.toml` in the configuration directory.
+pub const GLOBAL_CONFIGURATION_KEY: &str = "global";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct GlobalConfiguration {
+    pub name: String,
     pub lat: f32,
     pub lon: f32,
+    pub timezone: Tz,
+    pub units: Units,
+    pub locale: String,
+}
+
+impl Default for GlobalConfiguration {
+    fn default() -> Self {
+        Self {
+            name: "Låttitüdé".to_string(),
+            lat: 0.0,
+            lon: 0.0,
+            timezone: Tz::UTC,
+            units: Default::default(),
+            locale: "en-US".to_string(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::configuration::Configuration;
+    use toml::toml;
+
+    #[test]
+    fn deserialize_with_defaults() {
+        let configuration = Configuration::new(
+            GLOBAL_CONFIGURATION_KEY,
+            toml! {
+                name = "Kitchen"
+                lat = 38.9
+                lon = -77.0
+                timezone = "America/New_York"
+                units = "imperial"
+            }
+            .into(),
+        );
+
+        let global = configuration.deserialize::<GlobalConfiguration>().unwrap();
+        assert_eq!(global.name, "Kitchen");
+        assert_eq!(global.timezone, Tz::America__New_York);
+        assert_eq!(global.units, Units::Imperial);
+        assert_eq!(global.locale, "en-US");
+    }
+
+    #[test]
+    fn unknown_timezone() {
+        let configuration = Configuration::new(
+            GLOBAL_CONFIGURATION_KEY,
+            toml! {
+                timezone = "Mars/Olympus_Mons"
+            }
+            .into(),
+        );
+
+        let err = configuration
+            .deserialize::<GlobalConfiguration>()
+            .unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("timezone"));
+    }
 }
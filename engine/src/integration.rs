@@ -1,3 +1,5 @@
+use std::error::Error;
+use std::fmt::Debug;
 use std::future::Future;
 use std::hash::Hash;
 
@@ -7,6 +9,7 @@ use serde::Serialize;
 use crate::engine::integrations::IntegrationContext;
 use crate::global_configuration::GlobalConfiguration;
 
+#[derive(Clone, Debug)]
 pub struct IntegrationInfo {
     pub key: String,
     pub name: String,
@@ -21,14 +24,16 @@ impl IntegrationInfo {
     }
 }
 
+pub type UpdateError = Box<dyn Error + Send + Sync>;
+
 pub trait Integration: Send + Sync + 'static {
-    type Discriminant: Copy + Hash + PartialEq + Eq + Send + Sync;
+    type Discriminant: Copy + Debug + Hash + PartialEq + Eq + Send + Sync;
 
     type Configuration: Clone + Serialize + DeserializeOwned + Send + Sync;
 
     fn info() -> IntegrationInfo;
 
-    fn integrate(&self, context: &mut IntegrationContext<Self>)
+    fn integrate(&mut self, context: &mut IntegrationContext<Self>)
     where
         Self: Sized;
 
@@ -41,5 +46,18 @@ pub trait Integration: Send + Sync + 'static {
     fn update(
         &mut self,
         discriminant: Self::Discriminant,
-    ) -> impl Future<Output = ()> + Send + Sync;
+    ) -> impl Future<Output = Result<(), UpdateError>> + Send;
+
+    /// Open connections or spawn background tasks. Called after the
+    /// integration is configured and enabled, before its first update; a
+    /// failed start is retried in place of the next update.
+    fn start(&mut self) -> impl Future<Output = Result<(), UpdateError>> + Send {
+        async { Ok(()) }
+    }
+
+    /// Release whatever [`Integration::start`] acquired. Called when the
+    /// integration is disabled and when the engine shuts down.
+    fn stop(&mut self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
 }
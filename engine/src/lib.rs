@@ -10,9 +10,13 @@ pub mod model;
 
 pub mod global_configuration;
 
-mod configuration;
+pub mod clock;
+pub mod metrics;
+
+pub mod configuration;
 pub mod display;
 pub mod engine;
+pub mod testkit;
 
 pub fn page<F: Fn(&mut Canvas)>(configure: F) -> Page {
     let mut canvas = Canvas::new();
@@ -0,0 +1,278 @@
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The content type of [`Metrics::encode`].
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Update, render and display timings, exported in the Prometheus text
+/// exposition format. Clones share the same series.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    updates: BTreeMap<(String, String), UpdateSeries>,
+    renders: BTreeMap<String, Timing>,
+    pushes: BTreeMap<String, Timing>,
+}
+
+#[derive(Default)]
+struct UpdateSeries {
+    timing: Timing,
+    failures: u64,
+    last_success: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct Timing {
+    count: u64,
+    seconds: f64,
+}
+
+impl Timing {
+    fn record(&mut self, duration: Duration) {
+        self.count += 1;
+        self.seconds += duration.as_secs_f64();
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one update of an integration's controller, successful if it
+    /// has a completion time.
+    pub(crate) fn record_update(
+        &self,
+        integration: &str,
+        controller: &str,
+        duration: Duration,
+        succeeded_at: Option<DateTime<Utc>>,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        let series = inner
+            .updates
+            .entry((integration.to_string(), controller.to_string()))
+            .or_default();
+        series.timing.record(duration);
+        match succeeded_at {
+            Some(at) => series.last_success = Some(at),
+            None => series.failures += 1,
+        }
+    }
+
+    pub fn record_render(&self, page: &str, duration: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .renders
+            .entry(page.to_string())
+            .or_default()
+            .record(duration);
+    }
+
+    pub fn record_push(&self, display: &str, duration: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .pushes
+            .entry(display.to_string())
+            .or_default()
+            .record(duration);
+    }
+
+    /// Every series in the Prometheus text exposition format.
+    pub fn encode(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        let mut out = String::new();
+
+        let updates: Vec<_> = inner
+            .updates
+            .iter()
+            .map(|((integration, controller), series)| {
+                (
+                    labels(&[("integration", integration), ("controller", controller)]),
+                    series,
+                )
+            })
+            .collect();
+
+        family(
+            &mut out,
+            "lattitude_integration_updates_total",
+            "counter",
+            "Controller updates attempted.",
+        );
+        for (labels, series) in &updates {
+            sample(
+                &mut out,
+                "lattitude_integration_updates_total",
+                labels,
+                series.timing.count,
+            );
+        }
+
+        family(
+            &mut out,
+            "lattitude_integration_update_failures_total",
+            "counter",
+            "Controller updates that failed or timed out.",
+        );
+        for (labels, series) in &updates {
+            sample(
+                &mut out,
+                "lattitude_integration_update_failures_total",
+                labels,
+                series.failures,
+            );
+        }
+
+        timings(
+            &mut out,
+            "lattitude_integration_update_duration_seconds",
+            "Time spent in controller updates.",
+            updates
+                .iter()
+                .map(|(labels, series)| (labels.clone(), &series.timing)),
+        );
+
+        family(
+            &mut out,
+            "lattitude_integration_last_success_timestamp_seconds",
+            "gauge",
+            "When each controller last updated successfully.",
+        );
+        for (labels, series) in &updates {
+            if let Some(at) = series.last_success {
+                sample(
+                    &mut out,
+                    "lattitude_integration_last_success_timestamp_seconds",
+                    labels,
+                    at.timestamp_millis() as f64 / 1000.0,
+                );
+            }
+        }
+
+        timings(
+            &mut out,
+            "lattitude_page_render_duration_seconds",
+            "Time spent rendering pages.",
+            inner
+                .renders
+                .iter()
+                .map(|(page, timing)| (labels(&[("page", page)]), timing)),
+        );
+
+        timings(
+            &mut out,
+            "lattitude_display_push_duration_seconds",
+            "Time spent pushing frames to displays.",
+            inner
+                .pushes
+                .iter()
+                .map(|(display, timing)| (labels(&[("display", display)]), timing)),
+        );
+
+        out
+    }
+
+    /// Write [`Metrics::encode`] to `path`, e.g. for node_exporter's textfile
+    /// collector. The file is replaced atomically so scrapes never see it
+    /// half written.
+    pub async fn write(&self, path: &Path) -> io::Result<()> {
+        let partial = path.with_extension("prom.partial");
+        tokio::fs::write(&partial, self.encode()).await?;
+        tokio::fs::rename(&partial, path).await
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn sample<V: std::fmt::Display>(out: &mut String, name: &str, labels: &str, value: V) {
+    writeln!(out, "{}{{{}}} {}", name, labels, value).unwrap();
+}
+
+fn timings<'t>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    series: impl Iterator<Item = (String, &'t Timing)>,
+) {
+    family(out, name, "summary", help);
+    for (labels, timing) in series {
+        sample(out, &format!("{}_sum", name), &labels, timing.seconds);
+        sample(out, &format!("{}_count", name), &labels, timing.count);
+    }
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exposition_format() {
+        let metrics = Metrics::new();
+        let at = "2024-03-01T12:00:00Z".parse().unwrap();
+        metrics.record_update(
+            "accuweather",
+            "Hourly",
+            Duration::from_millis(250),
+            Some(at),
+        );
+        metrics.record_update("accuweather", "Hourly", Duration::from_millis(750), None);
+        metrics.record_render("Splash", Duration::from_millis(40));
+        metrics.record_push("Bmp \"main\"", Duration::from_millis(10));
+
+        let encoded = metrics.encode();
+        let series = "{integration=\"accuweather\",controller=\"Hourly\"}";
+        for line in [
+            "# TYPE lattitude_integration_updates_total counter".to_string(),
+            format!("lattitude_integration_updates_total{} 2", series),
+            format!("lattitude_integration_update_failures_total{} 1", series),
+            format!(
+                "lattitude_integration_update_duration_seconds_sum{} 1",
+                series
+            ),
+            format!(
+                "lattitude_integration_update_duration_seconds_count{} 2",
+                series
+            ),
+            format!(
+                "lattitude_integration_last_success_timestamp_seconds{} 1709294400",
+                series
+            ),
+            "lattitude_page_render_duration_seconds_count{page=\"Splash\"} 1".to_string(),
+            "lattitude_display_push_duration_seconds_sum{display=\"Bmp \\\"main\\\"\"} 0.01"
+                .to_string(),
+        ] {
+            assert!(
+                encoded.lines().any(|inner| inner == line),
+                "missing {}",
+                line
+            );
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use crate::clock::SharedClock;
+use crate::model::{Model, Sample};
+use std::any::{type_name, Any, TypeId};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+pub(crate) type Erased = Box<dyn Any + Send>;
+
+/// A registered [`Model`] with its type erased.
+pub(crate) trait ErasedModel: Send + Sync {
+    fn as_any(&self) -> &(dyn Any + Send + Sync);
+
+    fn value(&self) -> Pin<Box<dyn Future<Output = Option<Erased>> + Send + '_>>;
+
+    fn history(&self) -> Vec<Sample<Erased>>;
+
+    fn set_clock(&self, clock: SharedClock);
+
+    fn type_name(&self) -> &'static str;
+
+    /// The current value formatted with `Debug`.
+    fn describe(&self) -> Pin<Box<dyn Future<Output = Option<String>> + Send + '_>>;
+}
+
+impl<T> ErasedModel for Model<T>
+where
+    T: Clone + Sync + Send + Debug + 'static,
+{
+    fn as_any(&self) -> &(dyn Any + Send + Sync) {
+        self
+    }
+
+    fn value(&self) -> Pin<Box<dyn Future<Output = Option<Erased>> + Send + '_>> {
+        Box::pin(async move { self.get().await.map(|inner| -> Erased { Box::new(inner) }) })
+    }
+
+    fn history(&self) -> Vec<Sample<Erased>> {
+        Model::history(self)
+            .into_iter()
+            .map(|sample| sample.map(|inner| -> Erased { Box::new(inner) }))
+            .collect()
+    }
+
+    fn set_clock(&self, clock: SharedClock) {
+        Model::set_clock(self, clock)
+    }
+
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn describe(&self) -> Pin<Box<dyn Future<Output = Option<String>> + Send + '_>> {
+        Box::pin(async move { self.get().await.map(|inner| format!("{:?}", inner)) })
+    }
+}
+
+/// One hop through the model graph. `None` when the input is not what the
+/// converter expects or the conversion itself failed.
+pub(crate) trait Converter: Send + Sync {
+    fn convert(&self, input: Erased) -> Option<Erased>;
+
+    /// Whether the conversion may fail, i.e. goes through `TryFrom`.
+    fn fallible(&self) -> bool {
+        false
+    }
+}
+
+pub(crate) struct FromConverter<In, Out> {
+    _marker: PhantomData<fn(In) -> Out>,
+}
+
+impl<In, Out> FromConverter<In, Out> {
+    pub fn new() -> Self {
+        Self {
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<In, Out> Converter for FromConverter<In, Out>
+where
+    In: Send + 'static,
+    Out: From<In> + Send + 'static,
+{
+    fn convert(&self, input: Erased) -> Option<Erased> {
+        let input = input.downcast::<In>().ok()?;
+        Some(Box::new(Out::from(*input)))
+    }
+}
+
+pub(crate) struct TryFromConverter<In, Out> {
+    _marker: PhantomData<fn(In) -> Out>,
+}
+
+impl<In, Out> TryFromConverter<In, Out> {
+    pub fn new() -> Self {
+        Self {
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<In, Out> Converter for TryFromConverter<In, Out>
+where
+    In: Send + 'static,
+    Out: TryFrom<In> + Send + 'static,
+{
+    fn convert(&self, input: Erased) -> Option<Erased> {
+        let input = input.downcast::<In>().ok()?;
+        Some(Box::new(Out::try_from(*input).ok()?))
+    }
+
+    fn fallible(&self) -> bool {
+        true
+    }
+}
+
+/// A path from a primary model through zero or more conversions.
+#[derive(Clone)]
+pub(crate) struct Route<'g> {
+    pub(crate) origin: TypeId,
+    converters: Vec<&'g dyn Converter>,
+}
+
+impl Route<'_> {
+    pub(crate) fn apply(&self, value: Erased) -> Option<Erased> {
+        self.converters
+            .iter()
+            .try_fold(value, |value, converter| converter.convert(value))
+    }
+}
+
+struct ConverterEntry {
+    input_key: TypeId,
+    input_name: &'static str,
+    output_name: &'static str,
+    converter: Box<dyn Converter>,
+}
+
+/// A registered conversion between model types, see
+/// [`ModelManager::provides`](crate::model::ModelManager::provides).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conversion {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub fallible: bool,
+}
+
+/// Conversions between model types, keyed by output type.
+#[derive(Default)]
+pub(crate) struct ConverterGraph {
+    edges: HashMap<TypeId, Vec<ConverterEntry>>,
+}
+
+impl ConverterGraph {
+    /// Panics if the conversion would close a cycle, which is a programming
+    /// error best caught while registering.
+    pub(crate) fn add<In: 'static, Out: 'static>(&mut self, converter: Box<dyn Converter>) {
+        let input_key = TypeId::of::<In>();
+        let output_key = TypeId::of::<Out>();
+
+        if input_key == output_key || self.reaches(output_key, input_key) {
+            panic!(
+                "converting {} into {} would create a cycle",
+                type_name::<In>(),
+                type_name::<Out>()
+            );
+        }
+
+        self.edges
+            .entry(output_key)
+            .or_default()
+            .push(ConverterEntry {
+                input_key,
+                input_name: type_name::<In>(),
+                output_name: type_name::<Out>(),
+                converter,
+            });
+    }
+
+    /// Every conversion, sorted by input then output type name.
+    pub(crate) fn conversions(&self) -> Vec<Conversion> {
+        let mut conversions: Vec<_> = self
+            .edges
+            .values()
+            .flatten()
+            .map(|entry| Conversion {
+                from: entry.input_name,
+                to: entry.output_name,
+                fallible: entry.converter.fallible(),
+            })
+            .collect();
+        conversions.sort_by_key(|inner| (inner.from, inner.to));
+        conversions
+    }
+
+    /// Names of every type that can be derived from `origin`, directly or
+    /// through other conversions, sorted.
+    pub(crate) fn derivable_from(&self, origin: TypeId) -> Vec<&'static str> {
+        let mut names: Vec<_> = self
+            .edges
+            .iter()
+            .filter(|(output, _)| {
+                **output != origin
+                    && !self
+                        .collect_routes(**output, &|key| key == origin)
+                        .is_empty()
+            })
+            .filter_map(|(_, entries)| entries.first().map(|inner| inner.output_name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Every route to `output`, one per originating primary model. A primary
+    /// `output` is its own first route, followed by any conversions into it.
+    pub(crate) fn routes(&self, output: TypeId, is_primary: impl Fn(TypeId) -> bool) -> Vec<Route> {
+        let mut seen = HashSet::new();
+        self.collect_routes(output, &is_primary)
+            .into_iter()
+            .filter(|route| seen.insert(route.origin))
+            .collect()
+    }
+
+    fn collect_routes(&self, output: TypeId, is_primary: &impl Fn(TypeId) -> bool) -> Vec<Route> {
+        let mut routes = vec![];
+        if is_primary(output) {
+            routes.push(Route {
+                origin: output,
+                converters: vec![],
+            });
+        }
+
+        for entry in self.edges.get(&output).into_iter().flatten() {
+            for mut route in self.collect_routes(entry.input_key, is_primary) {
+                route.converters.push(entry.converter.as_ref());
+                routes.push(route);
+            }
+        }
+        routes
+    }
+
+    /// Whether `to` can be derived from `from`.
+    fn reaches(&self, from: TypeId, to: TypeId) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![from];
+
+        while let Some(current) = pending.pop() {
+            if current == to {
+                return true;
+            }
+            if visited.insert(current) {
+                pending.extend(self.edges.iter().filter_map(|(output, entries)| {
+                    entries
+                        .iter()
+                        .any(|inner| inner.input_key == current)
+                        .then_some(*output)
+                }));
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "cycle")]
+    fn cycles_are_rejected() {
+        let mut graph = ConverterGraph::default();
+        graph.add::<u8, u16>(Box::new(FromConverter::<u8, u16>::new()));
+        graph.add::<u16, u32>(Box::new(FromConverter::<u16, u32>::new()));
+        graph.add::<u32, u8>(Box::new(TryFromConverter::<u32, u8>::new()));
+    }
+
+    #[test]
+    fn routes_through_chains() {
+        let mut graph = ConverterGraph::default();
+        graph.add::<u16, u32>(Box::new(FromConverter::<u16, u32>::new()));
+        graph.add::<u8, u16>(Box::new(FromConverter::<u8, u16>::new()));
+        graph.add::<u32, i8>(Box::new(TryFromConverter::<u32, i8>::new()));
+
+        let is_primary = |key| key == TypeId::of::<u8>();
+        let routes = graph.routes(TypeId::of::<u32>(), is_primary);
+        assert_eq!(routes.len(), 1);
+        let converted = routes[0].apply(Box::new(7_u8)).unwrap();
+        assert_eq!(*converted.downcast::<u32>().unwrap(), 7);
+
+        // a failed conversion ends the route
+        let routes = graph.routes(TypeId::of::<i8>(), is_primary);
+        assert!(routes[0].apply(Box::new(200_u8)).is_none());
+        let converted = routes[0].apply(Box::new(100_u8)).unwrap();
+        assert_eq!(*converted.downcast::<i8>().unwrap(), 100);
+    }
+
+    #[test]
+    fn one_route_per_origin() {
+        let mut graph = ConverterGraph::default();
+        graph.add::<u8, u16>(Box::new(FromConverter::<u8, u16>::new()));
+        graph.add::<u8, u32>(Box::new(FromConverter::<u8, u32>::new()));
+        graph.add::<u16, u64>(Box::new(FromConverter::<u16, u64>::new()));
+        graph.add::<u32, u64>(Box::new(FromConverter::<u32, u64>::new()));
+
+        let routes = graph.routes(TypeId::of::<u64>(), |key| key == TypeId::of::<u8>());
+        assert_eq!(routes.len(), 1);
+    }
+
+    #[test]
+    fn primary_and_converted_routes() {
+        let mut graph = ConverterGraph::default();
+        graph.add::<u8, u16>(Box::new(FromConverter::<u8, u16>::new()));
+
+        let is_primary = |key| key == TypeId::of::<u8>() || key == TypeId::of::<u16>();
+        let routes = graph.routes(TypeId::of::<u16>(), is_primary);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].origin, TypeId::of::<u16>());
+        assert_eq!(routes[1].origin, TypeId::of::<u8>());
+        let converted = routes[1].apply(Box::new(7_u8)).unwrap();
+        assert_eq!(*converted.downcast::<u16>().unwrap(), 7);
+    }
+
+    #[test]
+    fn introspection() {
+        let mut graph = ConverterGraph::default();
+        graph.add::<u8, u16>(Box::new(FromConverter::<u8, u16>::new()));
+        graph.add::<u16, u32>(Box::new(FromConverter::<u16, u32>::new()));
+        graph.add::<u32, i8>(Box::new(TryFromConverter::<u32, i8>::new()));
+        graph.add::<i16, i32>(Box::new(FromConverter::<i16, i32>::new()));
+
+        assert_eq!(
+            graph.conversions(),
+            vec![
+                Conversion {
+                    from: "i16",
+                    to: "i32",
+                    fallible: false,
+                },
+                Conversion {
+                    from: "u16",
+                    to: "u32",
+                    fallible: false,
+                },
+                Conversion {
+                    from: "u32",
+                    to: "i8",
+                    fallible: true,
+                },
+                Conversion {
+                    from: "u8",
+                    to: "u16",
+                    fallible: false,
+                },
+            ]
+        );
+        assert_eq!(graph.derivable_from(TypeId::of::<u16>()), vec!["i8", "u32"]);
+        assert!(graph.derivable_from(TypeId::of::<i32>()).is_empty());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::model::{Model, ModelManager, ModelSubscription};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// A tuple of model types a derived model is computed from.
+///
+/// Each input is resolved through its [`Policy`](crate::model::Policy) and
+/// handed to the derivation as an `Option`, so it can decide for itself what
+/// to make of missing data.
+pub trait Inputs: Send + 'static {
+    type Values: Send;
+
+    fn subscribe(manager: &ModelManager) -> ModelSubscription;
+
+    fn resolve(manager: &ModelManager) -> Pin<Box<dyn Future<Output = Self::Values> + Send + '_>>;
+}
+
+macro_rules! inputs {
+    ($($input:ident),+) => {
+        impl<$($input),+> Inputs for ($($input,)+)
+        where
+            $($input: Debug + Clone + Sync + Send + 'static),+
+        {
+            type Values = ($(Option<$input>,)+);
+
+            fn subscribe(manager: &ModelManager) -> ModelSubscription {
+                let mut subscription = ModelSubscription::default();
+                $(subscription.merge(manager.subscribe::<$input>());)+
+                subscription
+            }
+
+            fn resolve(
+                manager: &ModelManager,
+            ) -> Pin<Box<dyn Future<Output = Self::Values> + Send + '_>> {
+                Box::pin(async move { ($(manager.resolve::<$input>().await,)+) })
+            }
+        }
+    };
+}
+
+inputs!(A);
+inputs!(A, B);
+inputs!(A, B, C);
+inputs!(A, B, C, D);
+
+pub(crate) trait Derivation: Send + Sync {
+    /// Subscribe to every current provider of the inputs, replacing the
+    /// previous subscription, and recompute on the next call since the new
+    /// providers may already hold values.
+    fn subscribe(&self, manager: &ModelManager);
+
+    /// Recompute if any input changed since the previous call, or always
+    /// for derivations that follow the clock. The first call always
+    /// computes.
+    fn recompute<'m>(
+        &'m self,
+        manager: &'m ModelManager,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'm>>;
+}
+
+pub(crate) struct DerivedModel<In, Out, F>
+where
+    Out: Clone + Sync + Send + Debug + 'static,
+{
+    model: Model<Out>,
+    derivation: F,
+    follows_clock: bool,
+    subscription: Mutex<ModelSubscription>,
+    resubscribed: AtomicBool,
+    _marker: std::marker::PhantomData<fn(In)>,
+}
+
+impl<In, Out, F> DerivedModel<In, Out, F>
+where
+    Out: Clone + Sync + Send + Debug + 'static,
+{
+    pub(crate) fn new(model: Model<Out>, derivation: F, follows_clock: bool) -> Self {
+        Self {
+            model,
+            derivation,
+            follows_clock,
+            subscription: Default::default(),
+            resubscribed: AtomicBool::new(true),
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<In, Out, F> Derivation for DerivedModel<In, Out, F>
+where
+    In: Inputs,
+    Out: Clone + Sync + Send + Debug + PartialEq + 'static,
+    F: Fn(DateTime<Tz>, In::Values) -> Option<Out> + Send + Sync + 'static,
+{
+    fn subscribe(&self, manager: &ModelManager) {
+        *self.subscription.lock().unwrap() = In::subscribe(manager);
+        self.resubscribed.store(true, Ordering::Release);
+    }
+
+    fn recompute<'m>(
+        &'m self,
+        manager: &'m ModelManager,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'm>> {
+        Box::pin(async move {
+            {
+                let mut subscription = self.subscription.lock().unwrap();
+                let changed = subscription.has_changed();
+                if changed {
+                    subscription.mark_seen();
+                }
+                let resubscribed = self.resubscribed.swap(false, Ordering::AcqRel);
+                if !(changed || resubscribed || self.follows_clock) {
+                    return;
+                }
+            }
+
+            let now = manager.now().with_timezone(&manager.timezone());
+            match (self.derivation)(now, In::resolve(manager).await) {
+                Some(value) => self.model.update(value).await,
+                None => {
+                    if self.model.get().await.is_some() {
+                        self.model.clear().await;
+                    }
+                }
+            }
+        })
+    }
+}
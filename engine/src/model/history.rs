@@ -0,0 +1,110 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::VecDeque;
+
+/// How much of a model's past to keep.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Retention {
+    /// The most recent values, however old.
+    Last(usize),
+    /// Every value no older than the window.
+    Within(Duration),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample<T> {
+    pub at: DateTime<Utc>,
+    pub value: T,
+}
+
+impl<T> Sample<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Sample<U> {
+        Sample {
+            at: self.at,
+            value: f(self.value),
+        }
+    }
+}
+
+pub(crate) struct History<T> {
+    retention: Retention,
+    samples: VecDeque<Sample<T>>,
+}
+
+impl<T: Clone> History<T> {
+    pub(crate) fn new(retention: Retention) -> Self {
+        Self {
+            retention,
+            samples: Default::default(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, at: DateTime<Utc>, value: T) {
+        self.samples.push_back(Sample { at, value });
+        self.prune(at);
+    }
+
+    /// Samples still within retention as of `now`, oldest first.
+    pub(crate) fn samples(&mut self, now: DateTime<Utc>) -> Vec<Sample<T>> {
+        self.prune(now);
+        self.samples.iter().cloned().collect()
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        match self.retention {
+            Retention::Last(count) => {
+                while self.samples.len() > count {
+                    self.samples.pop_front();
+                }
+            }
+            Retention::Within(window) => {
+                while self
+                    .samples
+                    .front()
+                    .map_or(false, |oldest| now - oldest.at > window)
+                {
+                    self.samples.pop_front();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keeps_last_count() {
+        let now = Utc::now();
+        let mut history = History::new(Retention::Last(2));
+        for value in 0..5 {
+            history.record(now, value);
+        }
+
+        let values: Vec<_> = history
+            .samples(now)
+            .into_iter()
+            .map(|inner| inner.value)
+            .collect();
+        assert_eq!(values, vec![3, 4]);
+    }
+
+    #[test]
+    fn keeps_window() {
+        let now = Utc::now();
+        let mut history = History::new(Retention::Within(Duration::hours(24)));
+        history.record(now - Duration::hours(30), 1);
+        history.record(now - Duration::hours(20), 2);
+        history.record(now - Duration::hours(1), 3);
+
+        let values: Vec<_> = history
+            .samples(now)
+            .into_iter()
+            .map(|inner| inner.value)
+            .collect();
+        assert_eq!(values, vec![2, 3]);
+
+        // ageing out happens on read, too
+        assert_eq!(history.samples(now + Duration::hours(10)).len(), 1);
+    }
+}
@@ -1,36 +1,228 @@
+mod conversion;
+mod derived;
+mod history;
+mod persistence;
+mod policy;
+mod subscription;
+
+pub use conversion::Conversion;
+pub use derived::Inputs;
+pub use history::{Retention, Sample};
+pub use policy::Policy;
+pub use subscription::ModelSubscription;
+
+use crate::clock::{SharedClock, SystemClock};
+use conversion::{ConverterGraph, ErasedModel, FromConverter, Route, TryFromConverter};
+use derived::{Derivation, DerivedModel};
+use history::History;
+use persistence::{Persist, PersistedModel};
+
 use std::any::{Any, TypeId};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
 use std::future::Future;
 use std::marker::PhantomData;
+use std::path::PathBuf;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
-use tokio::sync::Mutex;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::{watch, Mutex};
 
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct Model<T>
 where
     T: Clone + Sync + Send + Debug + 'static,
 {
     inner: Arc<Mutex<Option<T>>>,
+    version: Arc<watch::Sender<Revision>>,
+    history: Arc<std::sync::Mutex<Option<History<T>>>>,
+    clock: Arc<std::sync::RwLock<SharedClock>>,
+}
+
+impl<T> Default for Model<T>
+where
+    T: Clone + Sync + Send + Debug + 'static,
+{
+    fn default() -> Self {
+        Self {
+            inner: Default::default(),
+            version: Arc::new(watch::channel(Revision::default()).0),
+            history: Default::default(),
+            clock: Arc::new(std::sync::RwLock::new(Arc::new(SystemClock))),
+        }
+    }
 }
 
 impl<T> Model<T>
 where
     T: Clone + Sync + Send + Debug + 'static,
 {
-    pub async fn update(&self, value: T) {
-        self.inner.lock().await.replace(value);
+    /// Set the value and notify subscribers, unless it equals the current
+    /// one. Either way the model counts as freshly updated.
+    pub async fn update(&self, value: T)
+    where
+        T: PartialEq,
+    {
+        let now = self.now();
+        if let Some(history) = self.history.lock().unwrap().as_mut() {
+            history.record(now, value.clone());
+        }
+        let changed = {
+            let mut inner = self.inner.lock().await;
+            let changed = inner.as_ref() != Some(&value);
+            if changed {
+                inner.replace(value);
+            }
+            changed
+        };
+        self.version.send_if_modified(|revision| {
+            if changed {
+                revision.bump(now);
+            } else {
+                revision.updated_at.replace(now);
+            }
+            changed
+        });
     }
 
     pub async fn clear(&self) {
         self.inner.lock().await.take();
+        self.version
+            .send_modify(|revision| revision.bump(self.now()));
     }
 
     pub async fn get(&self) -> Option<T> {
         self.inner.lock().await.clone()
     }
+
+    /// Values of past updates, oldest first. Empty unless a [`Retention`]
+    /// was set when registering the model.
+    pub fn history(&self) -> Vec<Sample<T>> {
+        self.history
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(|inner| inner.samples(self.now()))
+            .unwrap_or_default()
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        self.clock.read().unwrap().now()
+    }
+
+    /// Timestamp updates by `clock`, shared by every clone of the model.
+    fn set_clock(&self, clock: SharedClock) {
+        *self.clock.write().unwrap() = clock;
+    }
+
+    fn set_retention(&self, retention: Retention) {
+        self.history
+            .lock()
+            .unwrap()
+            .replace(History::new(retention));
+    }
+
+    /// Restore a previously persisted value without touching its timestamp.
+    fn restore(&self, value: T, updated_at: Option<DateTime<Utc>>) {
+        // only ever called while registering, so nobody else holds the lock
+        if let Ok(mut inner) = self.inner.try_lock() {
+            inner.replace(value);
+            self.version.send_modify(|revision| {
+                revision.number += 1;
+                revision.updated_at = updated_at;
+            });
+        }
+    }
+
+    /// Incremented whenever the value changes or is cleared.
+    pub fn version(&self) -> u64 {
+        self.version.borrow().number
+    }
+
+    /// When the model was last updated or cleared.
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.version.borrow().updated_at
+    }
+
+    pub fn subscribe(&self) -> ModelSubscription {
+        ModelSubscription::new(vec![self.version.subscribe()])
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Revision {
+    number: u64,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl Revision {
+    fn bump(&mut self, now: DateTime<Utc>) {
+        self.number += 1;
+        self.updated_at.replace(now);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ModelMetadata {
+    /// Key of the integration providing the model.
+    pub provider: String,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub max_age: Option<Duration>,
+    /// The instant age and staleness are measured at, i.e. when the
+    /// metadata was read.
+    pub as_of: DateTime<Utc>,
+}
+
+impl ModelMetadata {
+    pub fn age(&self) -> Option<Duration> {
+        self.updated_at.map(|updated_at| self.as_of - updated_at)
+    }
+
+    /// Whether the model is older than its declared max-age. Models without
+    /// a max-age never go stale, models never updated always are.
+    pub fn is_stale(&self) -> bool {
+        match (self.age(), self.max_age) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(age), Some(max_age)) => age > max_age,
+        }
+    }
+}
+
+/// A model's value along with where and when it came from.
+#[derive(Clone, Debug)]
+pub struct ModelValue<T> {
+    pub value: Option<T>,
+    pub metadata: ModelMetadata,
+}
+
+impl<T> ModelValue<T> {
+    /// The value, unless it is stale.
+    pub fn fresh(self) -> Option<T> {
+        if self.metadata.is_stale() {
+            None
+        } else {
+            self.value
+        }
+    }
+}
+
+/// A registered model of any type, as shown to operators.
+#[derive(Clone, Debug)]
+pub struct ModelDescription {
+    pub type_name: &'static str,
+    /// The current value formatted with `Debug`.
+    pub value: Option<String>,
+    /// The current value as JSON, `None` unless the model was declared
+    /// serializable, see [`ModelManager::serializable`].
+    pub json: Option<serde_json::Value>,
+    /// Types converted from this model, see [`ModelManager::provides`].
+    pub provides: Vec<&'static str>,
+    pub metadata: ModelMetadata,
 }
 
 #[derive(Clone)]
@@ -38,191 +230,657 @@ pub struct ModelKey<T>
 where
     T: Clone + Debug + 'static,
 {
-    provider: TypeId,
+    provider: Option<String>,
     _marker: PhantomData<T>,
 }
 
+impl<T> ModelKey<T>
+where
+    T: Clone + Debug + 'static,
+{
+    /// Whichever provider the [`Policy`] for `T` prefers at the time of
+    /// lookup.
+    pub fn preferred() -> Self {
+        Self {
+            provider: None,
+            _marker: Default::default(),
+        }
+    }
+
+    /// The model of one particular provider, e.g. `birdnet.backyard`.
+    pub fn provided_by<P: Into<String>>(provider: P) -> Self {
+        Self {
+            provider: Some(provider.into()),
+            _marker: Default::default(),
+        }
+    }
+}
+
 impl<T> Debug for ModelKey<T>
 where
     T: Clone + Debug + 'static,
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.provider)
+        match &self.provider {
+            Some(provider) => write!(f, "{}", provider),
+            None => write!(f, "preferred"),
+        }
     }
 }
 
 #[derive(Default)]
 pub struct ModelManager {
-    primary: HashMap<TypeId, ProviderEntry>,
-    convertable: HashMap<TypeId, Vec<ConverterEntry>>,
+    primary: HashMap<TypeId, Vec<ProviderEntry>>,
+    convertable: ConverterGraph,
+    state_directory: Option<PathBuf>,
+    persisted: Vec<Box<dyn Persist>>,
+    policies: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    derived: Vec<Box<dyn Derivation>>,
+    clock: Option<SharedClock>,
+    timezone: RwLock<Option<Tz>>,
 }
 
+/// Provider key of models derived by the [`ModelManager`] itself.
+pub const DERIVED_PROVIDER: &str = "derived";
+
 impl ModelManager {
-    pub fn register<T>(&mut self, provider: TypeId, state: Model<T>)
+    /// Register `provider`'s model of `T`. Several providers may register
+    /// the same type, e.g. two instances of one integration; registering
+    /// again under the same provider replaces the model.
+    pub fn register<T>(&mut self, provider: &str, state: Model<T>)
     where
         T: Clone + Sync + Send + Debug + 'static,
     {
-        let key = TypeId::of::<T>();
+        state.set_clock(self.clock());
         let entry = ProviderEntry {
-            provider,
+            provider_key: provider.to_string(),
+            max_age: None,
+            version: state.version.clone(),
             state: Box::new(state),
+            to_json: None,
         };
-        self.primary.insert(key, entry);
+        let entries = self.primary.entry(TypeId::of::<T>()).or_default();
+        if let Some(existing) = entries
+            .iter_mut()
+            .find(|inner| inner.provider_key == provider)
+        {
+            *existing = entry;
+        } else {
+            entries.push(entry);
+        }
+        self.resubscribe();
     }
 
-    pub fn provides<Input, Output>(&mut self)
+    /// Let derivations pick up providers registered or converted since
+    /// they subscribed.
+    fn resubscribe(&self) {
+        for derived in &self.derived {
+            derived.subscribe(self);
+        }
+    }
+
+    /// Register a model computed from the models in `In`, e.g.
+    /// `(Temperature, Humidity)`, recomputed by [`ModelManager::recompute`]
+    /// whenever any of them changes, including providers registered later.
+    ///
+    /// The first derivation of an `Out` is provided by [`DERIVED_PROVIDER`],
+    /// further ones by `derived.2`, `derived.3` and so on, see
+    /// [`ModelManager::derived_key`].
+    pub fn derive<In, Out, F>(&mut self, derivation: F) -> Model<Out>
     where
-        Input: Debug + Clone + Sync + Send + 'static,
-        Output: Debug + From<Input> + 'static,
+        In: Inputs,
+        Out: Clone + Sync + Send + Debug + PartialEq + 'static,
+        F: Fn(In::Values) -> Option<Out> + Send + Sync + 'static,
     {
-        let converter = FromConverter::<Input, Output>::new();
+        self.add_derivation::<In, Out, _>(move |_, values| derivation(values), false)
+    }
 
-        let output_key = TypeId::of::<Output>();
-        let input_key = TypeId::of::<Input>();
+    /// Like [`ModelManager::derive`], for derivations that also depend on
+    /// the current time, e.g. "since sunrise". They are handed the clock's
+    /// time in the configured timezone, see [`ModelManager::set_timezone`],
+    /// and recomputed on every [`ModelManager::recompute`], whether or
+    /// not an input changed.
+    pub fn derive_with_clock<In, Out, F>(&mut self, derivation: F) -> Model<Out>
+    where
+        In: Inputs,
+        Out: Clone + Sync + Send + Debug + PartialEq + 'static,
+        F: Fn(DateTime<Tz>, In::Values) -> Option<Out> + Send + Sync + 'static,
+    {
+        self.add_derivation::<In, Out, F>(derivation, true)
+    }
+
+    fn add_derivation<In, Out, F>(&mut self, derivation: F, follows_clock: bool) -> Model<Out>
+    where
+        In: Inputs,
+        Out: Clone + Sync + Send + Debug + PartialEq + 'static,
+        F: Fn(DateTime<Tz>, In::Values) -> Option<Out> + Send + Sync + 'static,
+    {
+        let model = Model::<Out>::default();
+        let key = self.derived_key::<Out>();
+        self.register(&key, model.clone());
+        let derived = DerivedModel::<In, Out, F>::new(model.clone(), derivation, follows_clock);
+        derived.subscribe(self);
+        self.derived.push(Box::new(derived));
+        model
+    }
 
-        let entries = self.convertable.entry(output_key).or_default();
-        entries.push(ConverterEntry {
-            input_key,
-            converter: Box::new(converter),
+    /// The provider key the next derivation of an `Out` registers under.
+    pub fn derived_key<Out: 'static>(&self) -> String {
+        let derived = self.primary.get(&TypeId::of::<Out>()).map_or(0, |entries| {
+            entries
+                .iter()
+                .filter(|inner| {
+                    inner.provider_key == DERIVED_PROVIDER
+                        || inner
+                            .provider_key
+                            .strip_prefix(DERIVED_PROVIDER)
+                            .map_or(false, |suffix| suffix.starts_with('.'))
+                })
+                .count()
         });
+        match derived {
+            0 => DERIVED_PROVIDER.to_string(),
+            count => format!("{}.{}", DERIVED_PROVIDER, count + 1),
+        }
     }
 
-    pub fn providers_for<T>(&self) -> Vec<ModelKey<T>>
+    /// Bring derived models up to date with their inputs, in registration
+    /// order, so derived models may build upon each other.
+    pub async fn recompute(&self) {
+        for derived in &self.derived {
+            derived.recompute(self).await;
+        }
+    }
+
+    /// Directory in which persisted models are snapshotted. Must be set
+    /// before models are registered for their snapshots to be restored.
+    pub fn set_state_directory<P: Into<PathBuf>>(&mut self, path: P) {
+        self.state_directory.replace(path.into());
+    }
+
+    /// Take the current time from `clock` rather than the system clock,
+    /// for models registered before and after.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        for entry in self.primary.values().flatten() {
+            entry.state.set_clock(clock.clone());
+        }
+        self.clock.replace(clock);
+    }
+
+    /// The timezone clock-following derivations see the time in, e.g. to
+    /// tell when the day starts. Defaults to UTC.
+    pub fn set_timezone(&self, timezone: Tz) {
+        self.timezone.write().unwrap().replace(timezone);
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone.read().unwrap().unwrap_or(Tz::UTC)
+    }
+
+    /// The current time, as pages should see it.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock().now()
+    }
+
+    fn clock(&self) -> SharedClock {
+        self.clock.clone().unwrap_or_else(|| Arc::new(SystemClock))
+    }
+
+    /// Snapshot `provider`'s `T` to `<state directory>/<provider>/<name>.json`
+    /// on every [`ModelManager::save`], restoring the previous snapshot now.
+    ///
+    /// Does nothing without a state directory.
+    pub fn persist<T>(&mut self, provider: &str, name: &str)
     where
-        T: Debug + Clone + 'static,
+        T: Clone + Sync + Send + Debug + Serialize + DeserializeOwned + 'static,
     {
-        let mut keys = Vec::new();
-        if let Some(entries) = self.convertable.get(&TypeId::of::<T>()) {
-            for each in entries {
-                if let Some(primary) = self.primary.get(&each.input_key) {
-                    keys.push(ModelKey {
-                        provider: primary.provider,
-                        _marker: Default::default(),
-                    })
-                }
-            }
+        self.serializable::<T>(provider);
+
+        let Some(state_directory) = &self.state_directory else {
+            return;
+        };
+        let Some(entry) = self.entry::<T>(provider) else {
+            return;
+        };
+        let Some(model) = entry.state.as_any().downcast_ref::<Model<T>>() else {
+            return;
+        };
+
+        let path = state_directory
+            .join(&entry.provider_key)
+            .join(format!("{}.json", name));
+        let persisted = PersistedModel::new(model.clone(), path);
+        if let Err(err) = persisted.restore() {
+            log::warn!("unable to restore {}: {}", persisted.path().display(), err);
         }
+        self.persisted.push(Box::new(persisted));
+    }
 
-        keys
+    /// Every registered conversion between model types.
+    pub fn conversions(&self) -> Vec<Conversion> {
+        self.convertable.conversions()
     }
 
-    pub async fn get_all<T>(&self) -> Vec<Option<T>>
+    /// Every registered model, including derived ones, by provider then type.
+    pub async fn describe(&self) -> Vec<ModelDescription> {
+        let now = self.now();
+        let mut descriptions = Vec::new();
+        for (type_key, entries) in &self.primary {
+            let provides = self.convertable.derivable_from(*type_key);
+            for entry in entries {
+                let json = match &entry.to_json {
+                    Some(to_json) => Some(to_json().await),
+                    None => None,
+                };
+                descriptions.push(ModelDescription {
+                    type_name: entry.state.type_name(),
+                    value: entry.state.describe().await,
+                    json,
+                    provides: provides.clone(),
+                    metadata: entry.metadata(now),
+                });
+            }
+        }
+        descriptions.sort_by(|a, b| {
+            (&a.metadata.provider, a.type_name).cmp(&(&b.metadata.provider, b.type_name))
+        });
+        descriptions
+    }
+
+    /// Include `provider`'s `T` as JSON in [`ModelManager::describe`].
+    /// Persisted models are serializable already.
+    pub fn serializable<T>(&mut self, provider: &str)
     where
-        T: Debug + Clone + Sync + Send + 'static,
+        T: Clone + Sync + Send + Debug + Serialize + 'static,
     {
-        let key = TypeId::of::<T>();
+        let Some(entry) = self.entry_mut::<T>(provider) else {
+            return;
+        };
+        let Some(model) = entry.state.as_any().downcast_ref::<Model<T>>() else {
+            return;
+        };
+        let model = model.clone();
+        entry.to_json = Some(Box::new(move || {
+            let model = model.clone();
+            Box::pin(async move {
+                serde_json::to_value(model.get().await).unwrap_or_else(|err| {
+                    serde_json::Value::String(format!("cannot serialize: {}", err))
+                })
+            })
+        }));
+    }
 
-        if let Some(primary) = self.primary.get(&key) {
-            if let Some(value) = primary.state.downcast_ref::<Model<T>>() {
-                return vec![value.get().await];
+    /// Write snapshots of every persisted model that changed since it was
+    /// last saved.
+    pub async fn save(&self) {
+        for persisted in &self.persisted {
+            if let Err(err) = persisted.save().await {
+                log::warn!("unable to save {}: {}", persisted.path().display(), err);
             }
         }
+    }
 
-        let mut values = vec![];
-        if let Some(entries) = self.convertable.get(&key) {
-            for ConverterEntry {
-                input_key,
-                converter,
-            } in entries
-            {
-                if let Some(primary) = self.primary.get(input_key) {
-                    let output = converter.convert(&primary.state).await;
-                    if let Some(output) = output {
-                        if let Some(output) = output.downcast_ref::<Option<T>>() {
-                            values.push(output.clone())
-                        }
-                    }
-                }
+    /// Decide how [`ModelManager::resolve`] chooses among the providers of `T`.
+    pub fn set_policy<T>(&mut self, policy: Policy<T>)
+    where
+        T: 'static,
+    {
+        self.policies.insert(TypeId::of::<T>(), Box::new(policy));
+    }
+
+    /// Keep the history of `provider`'s `T` according to `retention`.
+    pub fn set_retention<T>(&mut self, provider: &str, retention: Retention)
+    where
+        T: Clone + Sync + Send + Debug + 'static,
+    {
+        if let Some(entry) = self.entry::<T>(provider) {
+            if let Some(model) = entry.state.as_any().downcast_ref::<Model<T>>() {
+                model.set_retention(retention);
             }
         }
+    }
+
+    /// Declare how long `provider`'s `T` stays fresh after each update.
+    pub fn set_max_age<T>(&mut self, provider: &str, max_age: Duration)
+    where
+        T: 'static,
+    {
+        if let Some(entry) = self.entry_mut::<T>(provider) {
+            entry.max_age.replace(max_age);
+        }
+    }
+
+    fn entry<T: 'static>(&self, provider: &str) -> Option<&ProviderEntry> {
+        self.primary
+            .get(&TypeId::of::<T>())?
+            .iter()
+            .find(|inner| inner.provider_key == provider)
+    }
+
+    fn entry_mut<T: 'static>(&mut self, provider: &str) -> Option<&mut ProviderEntry> {
+        self.primary
+            .get_mut(&TypeId::of::<T>())?
+            .iter_mut()
+            .find(|inner| inner.provider_key == provider)
+    }
+
+    pub fn provides<Input, Output>(&mut self)
+    where
+        Input: Debug + Clone + Sync + Send + 'static,
+        Output: Debug + From<Input> + Send + 'static,
+    {
+        self.convertable
+            .add::<Input, Output>(Box::new(FromConverter::<Input, Output>::new()));
+        self.resubscribe();
+    }
 
+    /// Like [`ModelManager::provides`], but a failed conversion yields no
+    /// value rather than requiring an infallible `From`.
+    pub fn try_provides<Input, Output>(&mut self)
+    where
+        Input: Debug + Clone + Sync + Send + 'static,
+        Output: Debug + TryFrom<Input> + Send + 'static,
+    {
+        self.convertable
+            .add::<Input, Output>(Box::new(TryFromConverter::<Input, Output>::new()));
+        self.resubscribe();
+    }
+
+    pub fn providers_for<T>(&self) -> Vec<ModelKey<T>>
+    where
+        T: Debug + Clone + 'static,
+    {
+        self.routes::<T>()
+            .into_iter()
+            .map(|(primary, _)| ModelKey {
+                provider: Some(primary.provider_key.clone()),
+                _marker: Default::default(),
+            })
+            .collect()
+    }
+
+    /// The key of `provider`'s `T`, directly or through conversions, or
+    /// `None` if `provider` cannot supply a `T`.
+    pub fn key_for<T>(&self, provider: &str) -> Option<ModelKey<T>>
+    where
+        T: Debug + Clone + 'static,
+    {
+        let key = ModelKey::provided_by(provider);
+        self.route_for(&key).map(|_| key)
+    }
+
+    /// Subscribe to changes of every model that can provide a `T`, either
+    /// directly or through registered conversions.
+    pub fn subscribe<T>(&self) -> ModelSubscription
+    where
+        T: 'static,
+    {
+        ModelSubscription::new(
+            self.routes::<T>()
+                .into_iter()
+                .map(|(primary, _)| primary.version.subscribe())
+                .collect(),
+        )
+    }
+
+    /// Subscribe to changes of the model behind `key`: only its provider's
+    /// model when bound to one, every candidate of the [`Policy`] when
+    /// preferred.
+    pub fn subscribe_key<T>(&self, key: &ModelKey<T>) -> ModelSubscription
+    where
+        T: Clone + Debug + 'static,
+    {
+        if key.provider.is_none() {
+            return self.subscribe::<T>();
+        }
+
+        ModelSubscription::new(
+            self.route_for(key)
+                .map(|(primary, _)| primary.version.subscribe())
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    pub async fn get_all<T>(&self) -> Vec<Option<T>>
+    where
+        T: Debug + Clone + Sync + Send + 'static,
+    {
+        self.get_all_with_metadata()
+            .await
+            .into_iter()
+            .map(|inner| inner.value)
+            .collect()
+    }
+
+    pub async fn get_all_with_metadata<T>(&self) -> Vec<ModelValue<T>>
+    where
+        T: Debug + Clone + Sync + Send + 'static,
+    {
+        let mut values = vec![];
+        for (primary, route) in self.routes::<T>() {
+            values.push(ModelValue {
+                value: Self::value_along(primary, &route).await,
+                metadata: primary.metadata(self.now()),
+            });
+        }
         values
     }
 
     pub async fn get<T>(&self, key: &ModelKey<T>) -> Option<T>
     where
-        T: Clone + Debug + 'static,
+        T: Clone + Debug + Sync + Send + 'static,
     {
-        if let Some(entries) = self.convertable.get(&TypeId::of::<T>()) {
-            if let Some(provider_entry) = entries.iter().find(|inner| {
-                if let Some(primary) = self.primary.get(&inner.input_key) {
-                    if primary.provider == key.provider {
-                        return true;
-                    }
-                }
-                false
-            }) {
-                if let Some(primary) = self.primary.get(&provider_entry.input_key) {
-                    let output = provider_entry.converter.convert(&primary.state).await;
-                    if let Some(output) = output {
-                        if let Some(output) = output.downcast_ref::<T>() {
-                            return Some(output.clone());
-                        }
-                    }
-                }
-            }
+        self.get_with_metadata(key)
+            .await
+            .and_then(|inner| inner.value)
+    }
+
+    pub async fn get_with_metadata<T>(&self, key: &ModelKey<T>) -> Option<ModelValue<T>>
+    where
+        T: Clone + Debug + Sync + Send + 'static,
+    {
+        if key.provider.is_none() {
+            return self.resolve_with_metadata().await;
         }
 
-        None
+        let (primary, route) = self.route_for(key)?;
+        Some(ModelValue {
+            value: Self::value_along(primary, &route).await,
+            metadata: primary.metadata(self.now()),
+        })
     }
-}
 
-struct ProviderEntry {
-    provider: TypeId,
-    state: Box<dyn Any>,
-}
+    /// Past values of the model behind `key`, oldest first.
+    pub async fn history<T>(&self, key: &ModelKey<T>) -> Vec<Sample<T>>
+    where
+        T: Clone + Debug + Sync + Send + 'static,
+    {
+        if key.provider.is_none() {
+            let mut histories = self.history_by_provider::<T>().await;
+            self.sort_by_preference::<T, _>(&mut histories, |(provider, _)| provider);
+            return histories
+                .into_iter()
+                .map(|(_, history)| history)
+                .find(|history| !history.is_empty())
+                .unwrap_or_default();
+        }
 
-struct ConverterEntry {
-    input_key: TypeId,
-    converter: Box<dyn Converter>,
-}
+        self.route_for(key)
+            .map(|(primary, route)| Self::history_along(primary, &route))
+            .unwrap_or_default()
+    }
 
-trait Converter {
-    fn convert<'i>(
-        &'i self,
-        input: &'i Box<dyn Any>,
-    ) -> Pin<Box<dyn Future<Output = Option<Box<dyn Any>>> + '_>>;
-}
+    /// Past values of every model that can provide a `T`, one history per
+    /// provider.
+    pub async fn history_all<T>(&self) -> Vec<Vec<Sample<T>>>
+    where
+        T: Debug + Clone + Sync + Send + 'static,
+    {
+        self.history_by_provider()
+            .await
+            .into_iter()
+            .map(|(_, history)| history)
+            .collect()
+    }
 
-struct FromConverter<In, Out> {
-    _marker: PhantomData<(In, Out)>,
-}
+    async fn history_by_provider<T>(&self) -> Vec<(String, Vec<Sample<T>>)>
+    where
+        T: Debug + Clone + Sync + Send + 'static,
+    {
+        self.routes::<T>()
+            .into_iter()
+            .map(|(primary, route)| {
+                (
+                    primary.provider_key.clone(),
+                    Self::history_along(primary, &route),
+                )
+            })
+            .collect()
+    }
 
-impl<In, Out> FromConverter<In, Out> {
-    pub fn new() -> Self {
-        Self {
-            _marker: Default::default(),
+    /// The value of `T` chosen by its [`Policy`], by default the first fresh
+    /// value in registration order.
+    ///
+    /// Falls back to the most preferred stale value when no provider has a
+    /// fresh one, since old data usually beats no data on a wall display.
+    pub async fn resolve<T>(&self) -> Option<T>
+    where
+        T: Debug + Clone + Sync + Send + 'static,
+    {
+        self.resolve_with_metadata()
+            .await
+            .and_then(|inner| inner.value)
+    }
+
+    pub async fn resolve_with_metadata<T>(&self) -> Option<ModelValue<T>>
+    where
+        T: Debug + Clone + Sync + Send + 'static,
+    {
+        let mut candidates: Vec<_> = self
+            .get_all_with_metadata::<T>()
+            .await
+            .into_iter()
+            .filter(|inner| inner.value.is_some())
+            .collect();
+        self.sort_by_preference::<T, _>(&mut candidates, |inner| &inner.metadata.provider);
+
+        let fresh: Vec<_> = candidates
+            .iter()
+            .filter(|inner| !inner.metadata.is_stale())
+            .cloned()
+            .collect();
+        let candidates = if fresh.is_empty() { candidates } else { fresh };
+
+        match self.policy::<T>() {
+            Some(Policy::Merge(_, merge)) if !candidates.is_empty() => {
+                let mut metadata = candidates[0].metadata.clone();
+                // merged data is only as fresh as its oldest part
+                metadata.updated_at = candidates
+                    .iter()
+                    .filter_map(|inner| inner.metadata.updated_at)
+                    .min();
+                let values = candidates.into_iter().filter_map(|inner| inner.value);
+                Some(ModelValue {
+                    value: merge(values.collect()),
+                    metadata,
+                })
+            }
+            _ => candidates.into_iter().next(),
+        }
+    }
+
+    fn policy<T: 'static>(&self) -> Option<&Policy<T>> {
+        self.policies
+            .get(&TypeId::of::<T>())
+            .and_then(|inner| inner.downcast_ref())
+    }
+
+    /// Stable-sort `items` by the preference of `T`'s policy for each
+    /// item's provider.
+    fn sort_by_preference<T: 'static, I>(&self, items: &mut [I], provider: impl Fn(&I) -> &String) {
+        if let Some(policy) = self.policy::<T>() {
+            items.sort_by_key(|inner| policy.rank(provider(inner)));
         }
     }
+
+    /// Every provider of `T` with its route, one per provider: a provider
+    /// registering `T` itself is not also offered through a conversion.
+    fn routes<T: 'static>(&self) -> Vec<(&ProviderEntry, Route)> {
+        let mut seen = HashSet::new();
+        self.convertable
+            .routes(TypeId::of::<T>(), |key| self.primary.contains_key(&key))
+            .into_iter()
+            .flat_map(|route| {
+                self.primary
+                    .get(&route.origin)
+                    .into_iter()
+                    .flatten()
+                    .map(move |primary| (primary, route.clone()))
+            })
+            .filter(|(primary, _)| seen.insert(primary.provider_key.as_str()))
+            .collect()
+    }
+
+    fn route_for<T: 'static>(&self, key: &ModelKey<T>) -> Option<(&ProviderEntry, Route)>
+    where
+        T: Clone + Debug,
+    {
+        self.routes::<T>()
+            .into_iter()
+            .find(|(primary, _)| key.provider.as_ref() == Some(&primary.provider_key))
+    }
+
+    async fn value_along<T: 'static>(primary: &ProviderEntry, route: &Route<'_>) -> Option<T> {
+        let value = primary.state.value().await?;
+        let value = route.apply(value)?.downcast::<T>().ok()?;
+        Some(*value)
+    }
+
+    fn history_along<T: 'static>(primary: &ProviderEntry, route: &Route) -> Vec<Sample<T>> {
+        primary
+            .state
+            .history()
+            .into_iter()
+            .filter_map(|sample| {
+                let value = route.apply(sample.value)?.downcast::<T>().ok()?;
+                Some(Sample {
+                    at: sample.at,
+                    value: *value,
+                })
+            })
+            .collect()
+    }
 }
 
-impl<In, Out> Converter for FromConverter<In, Out>
-where
-    In: Debug + Clone + Sync + Send + 'static,
-    Out: Debug + From<In> + 'static,
-{
-    fn convert<'i>(
-        &'i self,
-        input: &'i Box<dyn Any>,
-    ) -> Pin<Box<dyn Future<Output = Option<Box<dyn Any>>> + '_>> {
-        Box::pin(async move {
-            if let Some(input_value) = input.downcast_ref::<Model<In>>() {
-                let input_value = input_value.get().await;
-                let output_value: Option<Out> = input_value.clone().map(|inner| inner.into());
-                let boxed: Box<dyn Any> = Box::new(output_value);
-                return Some(boxed);
-            }
+type ToJson =
+    Box<dyn Fn() -> Pin<Box<dyn Future<Output = serde_json::Value> + Send>> + Send + Sync>;
 
-            None
-        })
+struct ProviderEntry {
+    provider_key: String,
+    max_age: Option<Duration>,
+    version: Arc<watch::Sender<Revision>>,
+    state: Box<dyn ErasedModel>,
+    to_json: Option<ToJson>,
+}
+
+impl ProviderEntry {
+    fn metadata(&self, as_of: DateTime<Utc>) -> ModelMetadata {
+        ModelMetadata {
+            provider: self.provider_key.clone(),
+            updated_at: self.version.borrow().updated_at,
+            max_age: self.max_age,
+            as_of,
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::clock::{Clock, ManualClock};
 
-    #[derive(Clone, Debug)]
+    #[derive(Clone, Debug, PartialEq, Serialize)]
     pub struct AccuWeather {
         wind_direction: u32,
         wind_speed: u32,
@@ -244,7 +902,7 @@ mod test {
         }
     }
 
-    #[derive(Clone, Debug)]
+    #[derive(Clone, Debug, PartialEq)]
     pub struct WeatherChannel {
         direction_of_the_wind: u32,
         speed_of_the_wind: u32,
@@ -271,67 +929,684 @@ mod test {
         dir: u32,
     }
 
-    #[derive(Clone, Debug)]
+    #[derive(Clone, Debug, PartialEq)]
     pub struct WindSpeed {
         speed: u32,
     }
 
-    #[derive(Clone, Debug)]
+    #[derive(Clone, Debug, PartialEq)]
     pub struct BirdNet {}
 
-    /*
-    #[tokio::test]
-    async fn whut() {
-        let mut manager = ModelManager::new();
+    fn manager() -> (ModelManager, Model<AccuWeather>, Model<WeatherChannel>) {
+        let mut manager = ModelManager::default();
 
-        let accuweather_provider = Provider::new("AccuWeather");
-        let weather_channel_provider = Provider::new("The Weather Channel");
-        let birdnet_provider = Provider::new("BirdNET");
+        let accuweather = Model::<AccuWeather>::default();
+        let weather_channel = Model::<WeatherChannel>::default();
 
-        let accuweather = Arc::new(Mutex::new(AccuWeather {
-            wind_direction: 180,
-            wind_speed: 200,
-        }));
-        let weather_channel = Arc::new(Mutex::new(WeatherChannel {
-            direction_of_the_wind: 188,
-            speed_of_the_wind: 110,
-        }));
-        let birdnet = Arc::new(Mutex::new(BirdNet {}));
-
-        manager.register(&accuweather_provider, accuweather.clone());
-        manager.register(&weather_channel_provider, weather_channel.clone());
-        manager.register(&birdnet_provider, birdnet.clone());
+        manager.register("accuweather", accuweather.clone());
+        manager.register("weather-channel", weather_channel.clone());
+        manager.register("birdnet", Model::<BirdNet>::default());
 
         manager.provides::<AccuWeather, WindDirection>();
         manager.provides::<WeatherChannel, WindDirection>();
         manager.provides::<AccuWeather, WindSpeed>();
         manager.provides::<WeatherChannel, WindSpeed>();
 
-        // not allowed
-        //manager.alias::<BirdNet, WindDirection>();
+        (manager, accuweather, weather_channel)
+    }
+
+    #[tokio::test]
+    async fn converted_values() {
+        let (manager, accuweather, weather_channel) = manager();
+
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 180,
+                wind_speed: 200,
+            })
+            .await;
+        weather_channel
+            .update(WeatherChannel {
+                direction_of_the_wind: 188,
+                speed_of_the_wind: 110,
+            })
+            .await;
 
         let keys = manager.providers_for::<WindSpeed>();
-        println!("{:#?}", keys);
+        assert_eq!(keys.len(), 2);
+
+        let mut speeds = Vec::new();
+        for key in &keys {
+            speeds.push(manager.get(key).await.unwrap().speed);
+        }
+        speeds.sort();
+        assert_eq!(speeds, vec![110, 200]);
+
+        let mut directions: Vec<_> = manager
+            .get_all::<WindDirection>()
+            .await
+            .into_iter()
+            .map(|inner| inner.unwrap().dir)
+            .collect();
+        directions.sort();
+        assert_eq!(directions, vec![180, 188]);
+
+        assert_eq!(manager.get_all::<BirdNet>().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn primary_and_converted_providers() {
+        let (mut manager, accuweather, _) = manager();
+        let anemometer = Model::<WindSpeed>::default();
+        manager.register("anemometer", anemometer.clone());
+
+        anemometer.update(WindSpeed { speed: 12 }).await;
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 200,
+            })
+            .await;
+
+        let providers: Vec<_> = manager
+            .providers_for::<WindSpeed>()
+            .into_iter()
+            .map(|inner| inner.provider.unwrap())
+            .collect();
+        assert_eq!(providers, ["anemometer", "accuweather", "weather-channel"]);
+
+        let accuweather = ModelKey::<WindSpeed>::provided_by("accuweather");
+        assert_eq!(manager.get(&accuweather).await.unwrap().speed, 200);
+        assert_eq!(manager.resolve::<WindSpeed>().await.unwrap().speed, 12);
+    }
+
+    #[tokio::test]
+    async fn describe_every_model() {
+        let (mut manager, accuweather, _) = manager();
+        manager.serializable::<AccuWeather>("accuweather");
+        manager.try_provides::<WindSpeed, Beaufort>();
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 90,
+                wind_speed: 10,
+            })
+            .await;
+
+        let descriptions = manager.describe().await;
+        let providers: Vec<_> = descriptions
+            .iter()
+            .map(|inner| inner.metadata.provider.as_str())
+            .collect();
+        assert_eq!(providers, vec!["accuweather", "birdnet", "weather-channel"]);
+        assert!(descriptions[0].type_name.ends_with("::AccuWeather"));
+        assert_eq!(
+            descriptions[0].value.as_deref(),
+            Some("AccuWeather { wind_direction: 90, wind_speed: 10 }")
+        );
+        assert_eq!(
+            descriptions[0].json,
+            Some(serde_json::json!({"wind_direction": 90, "wind_speed": 10}))
+        );
+        assert!(descriptions[0].provides[0].ends_with("::Beaufort"));
+        assert_eq!(descriptions[0].provides.len(), 3);
+        assert_eq!(descriptions[1].value, None);
+        assert_eq!(descriptions[1].json, None);
+        assert!(descriptions[1].provides.is_empty());
+
+        let fallible: Vec<_> = manager
+            .conversions()
+            .into_iter()
+            .filter(|inner| inner.fallible)
+            .collect();
+        assert_eq!(fallible.len(), 1);
+        assert!(fallible[0].from.ends_with("::WindSpeed"));
+    }
+
+    #[tokio::test]
+    async fn metadata_follows_provider() {
+        let (mut manager, accuweather, _) = manager();
+        manager.set_max_age::<AccuWeather>("accuweather", Duration::hours(1));
+
+        let values = manager.get_all_with_metadata::<AccuWeather>().await;
+        assert_eq!(values[0].metadata.provider, "accuweather");
+        assert!(values[0].metadata.updated_at.is_none());
+        // never updated, so already stale
+        assert!(values[0].metadata.is_stale());
+
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 90,
+                wind_speed: 5,
+            })
+            .await;
+
+        let key = ModelKey::<WindDirection>::provided_by("accuweather");
+        let direction = manager.get_with_metadata(&key).await.unwrap();
+        assert_eq!(direction.metadata.provider, "accuweather");
+        assert_eq!(direction.metadata.updated_at, accuweather.updated_at());
+        assert_eq!(direction.metadata.max_age, Some(Duration::hours(1)));
+        assert_eq!(direction.fresh().unwrap().dir, 90);
+
+        // converted models share the metadata of their input
+        let speeds = manager.get_all_with_metadata::<WindSpeed>().await;
+        assert!(speeds.iter().any(
+            |inner| inner.metadata.provider == "weather-channel" && !inner.metadata.is_stale()
+        ));
+    }
+
+    #[tokio::test]
+    async fn history_through_conversions() {
+        let (mut manager, accuweather, weather_channel) = manager();
+        manager.set_retention::<AccuWeather>("accuweather", Retention::Last(2));
+
+        for speed in [10, 20, 30] {
+            accuweather
+                .update(AccuWeather {
+                    wind_direction: 0,
+                    wind_speed: speed,
+                })
+                .await;
+            weather_channel
+                .update(WeatherChannel {
+                    direction_of_the_wind: 0,
+                    speed_of_the_wind: speed,
+                })
+                .await;
+        }
+
+        let speeds: Vec<_> = accuweather
+            .history()
+            .into_iter()
+            .map(|inner| inner.value.wind_speed)
+            .collect();
+        assert_eq!(speeds, vec![20, 30]);
+
+        let key = ModelKey::<WindSpeed>::provided_by("accuweather");
+        let history = manager.history(&key).await;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].value.speed, 30);
+        assert!(history[0].at <= history[1].at);
+
+        // the weather channel keeps no history
+        let histories = manager.history_all::<WindSpeed>().await;
+        let mut lengths: Vec<_> = histories.iter().map(Vec::len).collect();
+        lengths.sort();
+        assert_eq!(lengths, vec![0, 2]);
+    }
+
+    #[tokio::test]
+    async fn resolve_follows_policy() {
+        let (mut manager, accuweather, weather_channel) = manager();
+        let preferred = ModelKey::<WindSpeed>::preferred();
+
+        assert!(manager.resolve::<WindSpeed>().await.is_none());
+
+        weather_channel
+            .update(WeatherChannel {
+                direction_of_the_wind: 0,
+                speed_of_the_wind: 110,
+            })
+            .await;
+        // accuweather has nothing yet, so fall back
+        assert_eq!(manager.get(&preferred).await.unwrap().speed, 110);
+
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 200,
+            })
+            .await;
+        assert_eq!(manager.resolve::<WindSpeed>().await.unwrap().speed, 200);
 
-        for each in keys {
-            println!("{:?}", manager.get(&each).await);
+        manager.set_policy(Policy::<WindSpeed>::prefer(["weather-channel"]));
+        let resolved = manager.resolve_with_metadata::<WindSpeed>().await.unwrap();
+        assert_eq!(resolved.metadata.provider, "weather-channel");
+        assert_eq!(resolved.value.unwrap().speed, 110);
+
+        // the weather channel going stale hands over to accuweather
+        manager.set_max_age::<WeatherChannel>("weather-channel", Duration::zero());
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        assert_eq!(manager.resolve::<WindSpeed>().await.unwrap().speed, 200);
+
+        // unless everything is stale
+        manager.set_max_age::<AccuWeather>("accuweather", Duration::zero());
+        assert_eq!(manager.resolve::<WindSpeed>().await.unwrap().speed, 110);
+
+        manager.set_policy(Policy::<WindSpeed>::merge(|speeds| {
+            let count = speeds.len() as u32;
+            Some(WindSpeed {
+                speed: speeds.into_iter().map(|inner| inner.speed).sum::<u32>() / count,
+            })
+        }));
+        assert_eq!(manager.resolve::<WindSpeed>().await.unwrap().speed, 155);
+    }
+
+    #[tokio::test]
+    async fn prefer_converted_over_primary() {
+        let (mut manager, accuweather, _) = manager();
+        let anemometer = Model::<WindSpeed>::default();
+        manager.register("anemometer", anemometer.clone());
+
+        anemometer.update(WindSpeed { speed: 12 }).await;
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 200,
+            })
+            .await;
+        assert_eq!(manager.resolve::<WindSpeed>().await.unwrap().speed, 12);
+
+        manager.set_policy(Policy::<WindSpeed>::prefer(["accuweather"]));
+        let resolved = manager.resolve_with_metadata::<WindSpeed>().await.unwrap();
+        assert_eq!(resolved.metadata.provider, "accuweather");
+        assert_eq!(resolved.value.unwrap().speed, 200);
+
+        // a stale conversion falls back to the fresh primary provider
+        manager.set_max_age::<AccuWeather>("accuweather", Duration::zero());
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        assert_eq!(manager.resolve::<WindSpeed>().await.unwrap().speed, 12);
+    }
+
+    #[test]
+    fn staleness() {
+        let now = Utc::now();
+        let mut metadata = ModelMetadata {
+            provider: "accuweather".to_string(),
+            updated_at: Some(now - Duration::hours(2)),
+            max_age: None,
+            as_of: now,
+        };
+        assert!(!metadata.is_stale());
+
+        metadata.max_age.replace(Duration::hours(3));
+        assert!(!metadata.is_stale());
+
+        metadata.max_age.replace(Duration::hours(1));
+        assert!(metadata.is_stale());
+
+        let value = ModelValue {
+            value: Some(42),
+            metadata,
+        };
+        assert_eq!(value.fresh(), None);
+    }
+
+    #[tokio::test]
+    async fn staleness_follows_clock() {
+        let (mut manager, accuweather, _) = manager();
+        let clock = ManualClock::new("2024-03-01T12:00:00Z".parse().unwrap());
+        manager.set_clock(Arc::new(clock.clone()));
+        manager.set_max_age::<AccuWeather>("accuweather", Duration::hours(1));
+        manager.set_retention::<AccuWeather>("accuweather", Retention::Within(Duration::hours(2)));
+
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 1,
+            })
+            .await;
+        assert_eq!(accuweather.updated_at(), Some(clock.now()));
+
+        let key = ModelKey::<WindSpeed>::provided_by("accuweather");
+        clock.advance(Duration::minutes(59));
+        assert!(manager
+            .get_with_metadata(&key)
+            .await
+            .unwrap()
+            .fresh()
+            .is_some());
+
+        clock.advance(Duration::minutes(2));
+        let speed = manager.get_with_metadata(&key).await.unwrap();
+        assert_eq!(speed.metadata.age(), Some(Duration::minutes(61)));
+        assert!(speed.fresh().is_none());
+
+        // history ages out by the same clock
+        clock.advance(Duration::hours(1));
+        assert!(manager.history(&key).await.is_empty());
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Beaufort(u8);
+
+    impl TryFrom<WindSpeed> for Beaufort {
+        type Error = ();
+
+        fn try_from(value: WindSpeed) -> Result<Self, Self::Error> {
+            match value.speed {
+                0..=1 => Ok(Beaufort(0)),
+                2..=5 => Ok(Beaufort(1)),
+                _ => Err(()),
+            }
         }
+    }
 
-        let values = manager.get_all::<WindDirection>().await;
-        println!("{:#?}", values);
+    #[tokio::test]
+    async fn multi_hop_fallible_conversions() {
+        let (mut manager, accuweather, weather_channel) = manager();
+        manager.try_provides::<WindSpeed, Beaufort>();
 
-        accuweather.lock().await.wind_direction = 99;
-        accuweather.lock().await.wind_speed = 42;
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 4,
+            })
+            .await;
+        weather_channel
+            .update(WeatherChannel {
+                direction_of_the_wind: 0,
+                speed_of_the_wind: 400,
+            })
+            .await;
 
-        let values = manager.get_all::<WindDirection>().await;
-        println!("{:#?}", values);
+        assert_eq!(manager.providers_for::<Beaufort>().len(), 2);
+        let mut values = manager.get_all::<Beaufort>().await;
+        values.sort_by_key(|inner| inner.is_none());
+        // the weather channel's hurricane does not convert
+        assert_eq!(values, vec![Some(Beaufort(1)), None]);
 
-        let values = manager.get_all::<WindSpeed>().await;
-        println!("{:#?}", values);
+        // derived types notify like any other
+        let subscription = manager.subscribe::<Beaufort>();
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 1,
+            })
+            .await;
+        assert!(subscription.has_changed());
+        assert_eq!(manager.resolve::<Beaufort>().await, Some(Beaufort(0)));
+    }
 
-        let values = manager.get_all::<BirdNet>().await;
-        println!("{:#?}", values);
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Gust(u32);
+
+    impl From<Gust> for WindSpeed {
+        fn from(value: Gust) -> Self {
+            Self { speed: value.0 }
+        }
     }
 
-     */
+    #[tokio::test]
+    async fn derived_from_several_inputs() {
+        let (mut manager, accuweather, weather_channel) = manager();
+        manager.derive::<(AccuWeather, WeatherChannel), Gust, _>(
+            |(accuweather, weather_channel)| {
+                let (Some(accuweather), Some(weather_channel)) = (accuweather, weather_channel)
+                else {
+                    return None;
+                };
+                Some(Gust(
+                    accuweather
+                        .wind_speed
+                        .max(weather_channel.speed_of_the_wind),
+                ))
+            },
+        );
+        manager.provides::<Gust, WindSpeed>();
+
+        manager.recompute().await;
+        assert_eq!(manager.get_all::<Gust>().await, vec![None]);
+
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 10,
+            })
+            .await;
+        weather_channel
+            .update(WeatherChannel {
+                direction_of_the_wind: 0,
+                speed_of_the_wind: 30,
+            })
+            .await;
+
+        let gust = manager.subscribe::<Gust>();
+        manager.recompute().await;
+        assert!(gust.has_changed());
+        assert_eq!(manager.resolve::<Gust>().await, Some(Gust(30)));
+
+        // nothing changed, nothing recomputed
+        let gust = manager.subscribe::<Gust>();
+        manager.recompute().await;
+        assert!(!gust.has_changed());
+
+        let values = manager.get_all_with_metadata::<WindSpeed>().await;
+        assert!(values
+            .iter()
+            .any(|inner| inner.metadata.provider == DERIVED_PROVIDER
+                && inner.value.as_ref().map(|inner| inner.speed) == Some(30)));
+    }
+
+    #[tokio::test]
+    async fn derived_from_providers_registered_later() {
+        let mut manager = ModelManager::default();
+        manager.derive::<(WindSpeed,), Gust, _>(|(speed,)| speed.map(|inner| Gust(inner.speed)));
+        manager.recompute().await;
+        assert_eq!(manager.resolve::<Gust>().await, None);
+
+        let anemometer = Model::<WindSpeed>::default();
+        manager.register("anemometer", anemometer.clone());
+        manager.recompute().await;
+        anemometer.update(WindSpeed { speed: 12 }).await;
+        manager.recompute().await;
+        assert_eq!(manager.resolve::<Gust>().await, Some(Gust(12)));
+    }
+
+    #[tokio::test]
+    async fn derivations_of_one_type_keep_their_own_keys() {
+        let (mut manager, accuweather, _) = manager();
+        manager.derive::<(AccuWeather,), Gust, _>(|(accuweather,)| {
+            accuweather.map(|inner| Gust(inner.wind_speed))
+        });
+        manager.derive::<(AccuWeather,), Gust, _>(|(accuweather,)| {
+            accuweather.map(|inner| Gust(inner.wind_speed * 2))
+        });
+
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 10,
+            })
+            .await;
+        manager.recompute().await;
+
+        let gusts = manager.get_all_with_metadata::<Gust>().await;
+        let gusts: Vec<_> = gusts
+            .iter()
+            .map(|inner| (inner.metadata.provider.as_str(), inner.value.clone()))
+            .collect();
+        assert_eq!(
+            gusts,
+            vec![
+                (DERIVED_PROVIDER, Some(Gust(10))),
+                ("derived.2", Some(Gust(20)))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn derived_from_the_clock() {
+        use chrono::Datelike;
+
+        let (mut manager, accuweather, _) = manager();
+        let clock = ManualClock::new("2024-03-01T23:00:00Z".parse().unwrap());
+        manager.set_clock(Arc::new(clock.clone()));
+        // the day of the month, as long as there is any wind
+        manager.derive_with_clock::<(AccuWeather,), Gust, _>(|now, (accuweather,)| {
+            accuweather.map(|_| Gust(now.day()))
+        });
+
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 20,
+            })
+            .await;
+        manager.recompute().await;
+        assert_eq!(manager.resolve::<Gust>().await, Some(Gust(1)));
+
+        // no input changed, but the day did
+        clock.advance(Duration::hours(2));
+        manager.recompute().await;
+        assert_eq!(manager.resolve::<Gust>().await, Some(Gust(2)));
+
+        // the day is that of the configured timezone, not the host's
+        manager.set_timezone(Tz::America__Los_Angeles);
+        manager.recompute().await;
+        assert_eq!(manager.resolve::<Gust>().await, Some(Gust(1)));
+    }
+
+    #[tokio::test]
+    async fn update_bumps_version() {
+        let model = Model::<BirdNet>::default();
+        assert_eq!(model.version(), 0);
+
+        assert!(model.updated_at().is_none());
+
+        model.update(BirdNet {}).await;
+        model.clear().await;
+        assert_eq!(model.version(), 2);
+        assert!(model.updated_at().is_some());
+    }
+
+    #[tokio::test]
+    async fn unchanged_value_only_refreshes_timestamp() {
+        let clock = ManualClock::new("2024-03-01T12:00:00Z".parse().unwrap());
+        let model = Model::<BirdNet>::default();
+        model.set_clock(Arc::new(clock.clone()));
+        let subscription = model.subscribe();
+
+        model.update(BirdNet {}).await;
+        assert_eq!(model.version(), 1);
+        assert!(subscription.has_changed());
+
+        let subscription = model.subscribe();
+        clock.advance(Duration::minutes(5));
+        model.update(BirdNet {}).await;
+        assert_eq!(model.version(), 1);
+        assert!(!subscription.has_changed());
+        assert_eq!(model.updated_at(), Some(clock.now()));
+    }
+
+    #[tokio::test]
+    async fn subscription_follows_conversions() {
+        let (manager, accuweather, weather_channel) = manager();
+
+        let mut speed = manager.subscribe::<WindSpeed>();
+        let unrelated = manager.subscribe::<BirdNet>();
+        assert!(!speed.has_changed());
+
+        weather_channel
+            .update(WeatherChannel {
+                direction_of_the_wind: 1,
+                speed_of_the_wind: 2,
+            })
+            .await;
+        assert!(speed.has_changed());
+        assert!(!unrelated.has_changed());
+
+        speed.changed().await;
+        assert!(!speed.has_changed());
+
+        let waiter = tokio::spawn(async move {
+            speed.changed().await;
+        });
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 3,
+                wind_speed: 4,
+            })
+            .await;
+        tokio::time::timeout(std::time::Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn subscription_to_one_provider() {
+        let (manager, accuweather, weather_channel) = manager();
+
+        let key = ModelKey::<WindSpeed>::provided_by("weather-channel");
+        let speed = manager.subscribe_key(&key);
+        let preferred = manager.subscribe_key(&ModelKey::<WindSpeed>::preferred());
+
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 3,
+                wind_speed: 4,
+            })
+            .await;
+        assert!(!speed.has_changed());
+        assert!(preferred.has_changed());
+
+        weather_channel
+            .update(WeatherChannel {
+                direction_of_the_wind: 1,
+                speed_of_the_wind: 2,
+            })
+            .await;
+        assert!(speed.has_changed());
+    }
+
+    #[tokio::test]
+    async fn instances_of_one_type() {
+        let (mut manager, _, _) = manager();
+        let cabin = Model::<AccuWeather>::default();
+        manager.register("accuweather.cabin", cabin.clone());
+
+        cabin
+            .update(AccuWeather {
+                wind_direction: 0,
+                wind_speed: 12,
+            })
+            .await;
+
+        // both instances provide, neither overwrites the other
+        assert_eq!(manager.get_all::<AccuWeather>().await.len(), 2);
+        assert_eq!(manager.providers_for::<WindSpeed>().len(), 3);
+
+        let key = ModelKey::<WindSpeed>::provided_by("accuweather.cabin");
+        assert_eq!(manager.get(&key).await.unwrap().speed, 12);
+        let missing = ModelKey::<WindSpeed>::provided_by("accuweather.attic");
+        assert!(manager.get(&missing).await.is_none());
+
+        // listing the integration ranks every instance
+        manager.set_policy(Policy::<WindSpeed>::prefer([
+            "weather-channel",
+            "accuweather",
+        ]));
+        let resolved = manager.resolve_with_metadata::<WindSpeed>().await.unwrap();
+        assert_eq!(resolved.metadata.provider, "accuweather.cabin");
+    }
+
+    #[tokio::test]
+    async fn key_lookup() {
+        let (manager, accuweather, _) = manager();
+        accuweather
+            .update(AccuWeather {
+                wind_direction: 270,
+                wind_speed: 3,
+            })
+            .await;
+
+        let key = manager.key_for::<WindDirection>("accuweather").unwrap();
+        assert_eq!(manager.get(&key).await.unwrap().dir, 270);
+
+        assert!(manager.key_for::<WindDirection>("birdnet").is_none());
+        assert!(manager
+            .key_for::<WindDirection>("accuweather.cabin")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn empty_subscription_never_fires() {
+        let mut subscription = ModelSubscription::default();
+        assert!(subscription.is_empty());
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(10), subscription.changed())
+                .await
+                .is_err()
+        );
+    }
 }
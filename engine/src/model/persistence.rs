@@ -0,0 +1,135 @@
+use crate::model::Model;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::fs::{self, File};
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot<T> {
+    updated_at: Option<DateTime<Utc>>,
+    value: Option<T>,
+}
+
+pub(crate) trait Persist: Send + Sync {
+    /// Write the snapshot if the model changed since it was last written.
+    fn save(&self) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + '_>>;
+
+    fn path(&self) -> &PathBuf;
+}
+
+pub(crate) struct PersistedModel<T>
+where
+    T: Clone + Sync + Send + Debug + 'static,
+{
+    model: Model<T>,
+    path: PathBuf,
+    saved: AtomicU64,
+}
+
+impl<T> PersistedModel<T>
+where
+    T: Clone + Sync + Send + Debug + Serialize + DeserializeOwned + 'static,
+{
+    pub(crate) fn new(model: Model<T>, path: PathBuf) -> Self {
+        Self {
+            saved: AtomicU64::new(model.version()),
+            model,
+            path,
+        }
+    }
+
+    /// Restore the snapshot into the model, unless the model was already
+    /// updated.
+    pub(crate) fn restore(&self) -> io::Result<()> {
+        if self.model.version() == 0 && self.path.exists() {
+            let snapshot: Snapshot<T> = serde_json::from_reader(File::open(&self.path)?)?;
+            if let Some(value) = snapshot.value {
+                self.model.restore(value, snapshot.updated_at);
+            }
+            // nothing new to write until the next update
+            self.saved.store(self.model.version(), Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+impl<T> Persist for PersistedModel<T>
+where
+    T: Clone + Sync + Send + Debug + Serialize + 'static,
+{
+    fn save(&self) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + '_>> {
+        Box::pin(async move {
+            let version = self.model.version();
+            if self.saved.load(Ordering::Acquire) == version {
+                return Ok(());
+            }
+
+            let snapshot = Snapshot {
+                updated_at: self.model.updated_at(),
+                value: self.model.get().await,
+            };
+
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // write aside and rename, so a crash never leaves half a snapshot
+            let partial = self.path.with_extension("json.partial");
+            serde_json::to_writer(File::create(&partial)?, &snapshot)?;
+            fs::rename(&partial, &self.path)?;
+
+            self.saved.store(version, Ordering::Release);
+            Ok(())
+        })
+    }
+
+    fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::model::{Model, ModelManager};
+    use serde::{Deserialize, Serialize};
+    use std::fs;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Forecast {
+        high: i32,
+    }
+
+    fn register(state_directory: &std::path::Path) -> (ModelManager, Model<Forecast>) {
+        let mut manager = ModelManager::default();
+        manager.set_state_directory(state_directory);
+        let model = Model::<Forecast>::default();
+        manager.register("accuweather", model.clone());
+        manager.persist::<Forecast>("accuweather", "daily");
+        (manager, model)
+    }
+
+    #[tokio::test]
+    async fn snapshot_survives_restart() {
+        let base = std::env::temp_dir().join(format!("lattitude-state-{}", std::process::id()));
+        fs::remove_dir_all(&base).ok();
+
+        let (manager, model) = register(&base);
+        assert_eq!(model.get().await, None);
+
+        model.update(Forecast { high: 21 }).await;
+        let updated_at = model.updated_at();
+        manager.save().await;
+        assert!(base.join("accuweather").join("daily.json").exists());
+
+        let (_, restored) = register(&base);
+        assert_eq!(restored.get().await, Some(Forecast { high: 21 }));
+        assert_eq!(restored.updated_at(), updated_at);
+
+        fs::remove_dir_all(&base).ok();
+    }
+}
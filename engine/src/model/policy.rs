@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+/// How [`ModelManager::resolve`](crate::model::ModelManager::resolve) picks a
+/// value when several providers can supply the same type.
+pub enum Policy<T> {
+    /// Provider keys in order of preference. Providers not listed follow in
+    /// registration order. The first fresh value wins.
+    Prefer(Vec<String>),
+    /// Combine every fresh value, in provider preference order.
+    Merge(Vec<String>, Arc<dyn Fn(Vec<T>) -> Option<T> + Send + Sync>),
+}
+
+impl<T> Policy<T> {
+    pub fn prefer<I: IntoIterator<Item = S>, S: Into<String>>(providers: I) -> Self {
+        Self::Prefer(providers.into_iter().map(Into::into).collect())
+    }
+
+    pub fn merge<F>(merge: F) -> Self
+    where
+        F: Fn(Vec<T>) -> Option<T> + Send + Sync + 'static,
+    {
+        Self::Merge(vec![], Arc::new(merge))
+    }
+
+    pub(crate) fn preference(&self) -> &[String] {
+        match self {
+            Policy::Prefer(preference) => preference,
+            Policy::Merge(preference, _) => preference,
+        }
+    }
+
+    /// Rank of `provider` in the preference list, unlisted providers last.
+    /// Listing an integration key, e.g. `birdnet`, ranks all its instances.
+    pub(crate) fn rank(&self, provider: &str) -> usize {
+        let preference = self.preference();
+        preference
+            .iter()
+            .position(|inner| {
+                inner == provider
+                    || provider
+                        .strip_prefix(inner.as_str())
+                        .map_or(false, |instance| instance.starts_with('.'))
+            })
+            .unwrap_or(preference.len())
+    }
+}
+
+impl<T> Default for Policy<T> {
+    fn default() -> Self {
+        Self::Prefer(vec![])
+    }
+}
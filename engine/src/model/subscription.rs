@@ -0,0 +1,64 @@
+use crate::model::Revision;
+use futures::future::select_all;
+use tokio::sync::watch;
+
+/// Notifies when any of a set of models changes.
+///
+/// An empty subscription never fires.
+#[derive(Default)]
+pub struct ModelSubscription {
+    receivers: Vec<watch::Receiver<Revision>>,
+}
+
+impl ModelSubscription {
+    pub(crate) fn new(receivers: Vec<watch::Receiver<Revision>>) -> Self {
+        Self { receivers }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.receivers.is_empty()
+    }
+
+    pub fn merge(&mut self, other: ModelSubscription) {
+        self.receivers.extend(other.receivers);
+    }
+
+    /// Whether any model changed since the subscription was created or
+    /// [`ModelSubscription::changed`] last returned.
+    pub fn has_changed(&self) -> bool {
+        self.receivers
+            .iter()
+            .any(|inner| inner.has_changed().unwrap_or(false))
+    }
+
+    pub(crate) fn mark_seen(&mut self) {
+        for receiver in &mut self.receivers {
+            receiver.borrow_and_update();
+        }
+    }
+
+    /// Wait until any subscribed model changes.
+    pub async fn changed(&mut self) {
+        loop {
+            if self.receivers.is_empty() {
+                return std::future::pending().await;
+            }
+
+            let (result, index, _) = select_all(
+                self.receivers
+                    .iter_mut()
+                    .map(|inner| Box::pin(inner.changed())),
+            )
+            .await;
+
+            if result.is_ok() {
+                // consume concurrent changes, so they do not fire again
+                self.mark_seen();
+                return;
+            }
+
+            // the model went away and will never change again
+            self.receivers.swap_remove(index);
+        }
+    }
+}
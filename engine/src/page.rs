@@ -1,4 +1,4 @@
-use crate::model::ModelManager;
+use crate::model::{ModelManager, ModelSubscription};
 use crate::view::canvas::Canvas;
 use crate::view::Renderable;
 use pixelfield::pixelfield::PixelField;
@@ -27,6 +27,11 @@ impl Page {
     ) -> Pin<Box<dyn Future<Output = PixelField> + 'r>> {
         Box::pin(async move { self.canvas.render(state_manager).await.unwrap_or_default() })
     }
+
+    /// Changes to any model the page renders.
+    pub fn subscribe(&self, state_manager: &ModelManager) -> ModelSubscription {
+        self.canvas.subscribe(state_manager)
+    }
 }
 
 #[derive(Default)]
@@ -58,4 +63,11 @@ where
             PixelField::default()
         }
     }
+
+    pub fn subscribe(&self, state_manager: &ModelManager, id: PageId) -> ModelSubscription {
+        self.pages
+            .get(&id)
+            .map(|page| page.subscribe(state_manager))
+            .unwrap_or_default()
+    }
 }
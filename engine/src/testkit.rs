@@ -0,0 +1,299 @@
+use crate::clock::{Clock, ManualClock};
+use crate::configuration::{Configuration, ConfigurationError};
+use crate::engine::health::IntegrationHealth;
+pub use crate::engine::integrations::Update;
+use crate::engine::Engine;
+use crate::integration::Integration;
+use crate::model::ModelManager;
+use chrono::{DateTime, Duration, Utc};
+use std::fmt::Debug;
+use toml::Value;
+
+/// Where a [`TestKit`]'s clock starts unless told otherwise.
+pub const START: &str = "2024-01-01T00:00:00Z";
+
+/// Drives integrations through an [`Engine`] on a [`ManualClock`], without
+/// a configuration directory or a running loop, so integration authors can
+/// test cadence and model wiring without network access.
+///
+/// Time only moves through [`TestKit::advance`], and each tick reports the
+/// controllers it ran.
+pub struct TestKit {
+    engine: Engine,
+    clock: ManualClock,
+}
+
+impl Default for TestKit {
+    fn default() -> Self {
+        Self::starting_at(START.parse().unwrap())
+    }
+}
+
+impl TestKit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn starting_at(now: DateTime<Utc>) -> Self {
+        let clock = ManualClock::new(now);
+        let mut engine = Engine::new();
+        engine.set_clock(clock.clone());
+        Self { engine, clock }
+    }
+
+    pub fn register<I: Integration>(&mut self, integration: I) {
+        self.engine.register(integration);
+    }
+
+    /// The engine under test, e.g. to register instances, derivations or
+    /// policies.
+    pub fn engine(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    /// Configure the integration registered under `key` as if `<key>.toml`
+    /// contained `toml`. The first call also configures every other
+    /// integration, without a configuration.
+    pub async fn configure(&self, key: &str, toml: &str) -> Result<(), Vec<ConfigurationError>> {
+        let configuration = toml::from_str(toml)
+            .map(|content| Configuration::new(key, Value::Table(content)))
+            .map_err(|err| ConfigurationError {
+                key: key.to_string(),
+                path: None,
+                field: None,
+                message: err.to_string(),
+            });
+        self.engine.apply_configuration(vec![configuration]).await
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Move the clock forward, then [`TestKit::tick`].
+    pub async fn advance(&self, duration: Duration) -> Updates {
+        self.clock.advance(duration);
+        self.tick().await
+    }
+
+    /// Run one round of the engine at the current time: update whatever is
+    /// due, recompute derived models and save snapshots.
+    pub async fn tick(&self) -> Updates {
+        Updates {
+            updates: self.engine.tick().await,
+        }
+    }
+
+    pub fn model_manager(&self) -> &ModelManager {
+        self.engine.model_manager()
+    }
+
+    /// The value pages would get for `T`, see [`ModelManager::resolve`].
+    pub async fn resolve<T>(&self) -> Option<T>
+    where
+        T: Debug + Clone + Sync + Send + 'static,
+    {
+        self.engine.model_manager().resolve().await
+    }
+
+    /// The `T` provided by the integration registered under `provider`.
+    pub async fn model<T>(&self, provider: &str) -> Option<T>
+    where
+        T: Debug + Clone + Sync + Send + 'static,
+    {
+        let model_manager = self.engine.model_manager();
+        model_manager.get(&model_manager.key_for(provider)?).await
+    }
+
+    pub async fn health(&self) -> Vec<IntegrationHealth> {
+        self.engine.health().await
+    }
+}
+
+/// Every controller run during a [`TestKit::tick`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Updates {
+    updates: Vec<Update>,
+}
+
+impl Updates {
+    /// Whether the `discriminant` controller of `integration` ran,
+    /// successfully or not.
+    pub fn contains<D: Debug>(&self, integration: &str, discriminant: D) -> bool {
+        self.find(integration, discriminant).is_some()
+    }
+
+    /// Whether the `discriminant` controller of `integration` ran and failed.
+    pub fn failed<D: Debug>(&self, integration: &str, discriminant: D) -> bool {
+        self.find(integration, discriminant)
+            .map_or(false, |inner| inner.error.is_some())
+    }
+
+    /// Names of the controllers of `integration` that ran, sorted.
+    pub fn controllers(&self, integration: &str) -> Vec<&str> {
+        let mut controllers = self
+            .updates
+            .iter()
+            .filter(|inner| inner.integration == integration)
+            .map(|inner| inner.controller.as_str())
+            .collect::<Vec<_>>();
+        controllers.sort();
+        controllers
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Update> {
+        self.updates.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.updates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    fn find<D: Debug>(&self, integration: &str, discriminant: D) -> Option<&Update> {
+        let controller = format!("{:?}", discriminant);
+        self.updates
+            .iter()
+            .find(|inner| inner.integration == integration && inner.controller == controller)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engine::integrations::IntegrationContext;
+    use crate::global_configuration::GlobalConfiguration;
+    use crate::integration::{IntegrationInfo, UpdateError};
+    use crate::model::Model;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Default)]
+    struct Thermometer {
+        offset: i32,
+        reading: Model<i32>,
+        calibrations: Model<u32>,
+    }
+
+    #[derive(Hash, Eq, PartialEq, Copy, Clone, Debug)]
+    enum ThermometerControllers {
+        Reading,
+        Calibrate,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    struct ThermometerConfiguration {
+        offset: i32,
+    }
+
+    impl Integration for Thermometer {
+        type Discriminant = ThermometerControllers;
+        type Configuration = ThermometerConfiguration;
+
+        fn info() -> IntegrationInfo {
+            IntegrationInfo::new("thermometer", "Thermometer")
+        }
+
+        fn integrate(&mut self, context: &mut IntegrationContext<Self>)
+        where
+            Self: Sized,
+        {
+            context.register_controller(ThermometerControllers::Reading, Duration::minutes(5));
+            context.register_controller(ThermometerControllers::Calibrate, Duration::hours(1));
+            context.register_model(self.reading.clone());
+            context.register_model(self.calibrations.clone());
+        }
+
+        async fn configure(
+            &mut self,
+            _global_configuration: GlobalConfiguration,
+            integration_configuration: Option<Self::Configuration>,
+        ) {
+            self.offset = integration_configuration.map_or(0, |inner| inner.offset);
+        }
+
+        async fn update(&mut self, discriminant: Self::Discriminant) -> Result<(), UpdateError> {
+            match discriminant {
+                ThermometerControllers::Reading if self.offset < 0 => {
+                    return Err("sensor offline".into())
+                }
+                ThermometerControllers::Reading => self.reading.update(20 + self.offset).await,
+                ThermometerControllers::Calibrate => {
+                    let calibrations = self.calibrations.get().await.unwrap_or_default();
+                    self.calibrations.update(calibrations + 1).await
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn cadence_and_models() {
+        let mut kit = TestKit::new();
+        kit.register(Thermometer::default());
+        kit.configure("thermometer", "offset = 2").await.unwrap();
+
+        let updates = kit.tick().await;
+        assert_eq!(
+            updates.controllers("thermometer"),
+            vec!["Calibrate", "Reading"]
+        );
+        assert_eq!(kit.resolve::<i32>().await, Some(22));
+        assert!(kit.tick().await.is_empty());
+
+        let updates = kit.advance(Duration::minutes(5)).await;
+        assert!(updates.contains("thermometer", ThermometerControllers::Reading));
+        assert!(!updates.contains("thermometer", ThermometerControllers::Calibrate));
+
+        let updates = kit.advance(Duration::minutes(55)).await;
+        assert_eq!(updates.len(), 2);
+        assert_eq!(kit.model::<u32>("thermometer").await, Some(2));
+
+        let metadata = kit
+            .model_manager()
+            .resolve_with_metadata::<u32>()
+            .await
+            .unwrap()
+            .metadata;
+        assert_eq!(metadata.updated_at, Some(kit.now()));
+    }
+
+    #[tokio::test]
+    async fn refresh_through_the_handle() {
+        let mut kit = TestKit::new();
+        kit.register(Thermometer::default());
+        kit.tick().await;
+
+        let handle = kit.engine().handle();
+        // at the same instant, so only the update log tells them apart
+        handle.refresh("thermometer", Some("Calibrate"));
+        let updates = kit.tick().await;
+        assert_eq!(updates.controllers("thermometer"), vec!["Calibrate"]);
+        assert_eq!(kit.model::<u32>("thermometer").await, Some(2));
+
+        handle.refresh_all();
+        assert_eq!(kit.tick().await.len(), 2);
+        assert!(kit.tick().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn failures_and_configuration_errors() {
+        let mut kit = TestKit::new();
+        kit.register(Thermometer::default());
+
+        let errors = kit
+            .configure("thermometer", "offset = \"warm\"")
+            .await
+            .unwrap_err();
+        assert_eq!(errors[0].field.as_deref(), Some("offset"));
+        assert!(kit.configure("thermometer", "offset =").await.is_err());
+
+        kit.configure("thermometer", "offset = -1").await.unwrap();
+        let updates = kit.tick().await;
+        assert!(updates.failed("thermometer", ThermometerControllers::Reading));
+        assert!(!updates.failed("thermometer", ThermometerControllers::Calibrate));
+        assert_eq!(kit.resolve::<i32>().await, None);
+    }
+}
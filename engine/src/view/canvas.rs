@@ -1,4 +1,4 @@
-use crate::model::ModelManager;
+use crate::model::{ModelManager, ModelSubscription};
 use crate::view::{HorizontalAlignment, Renderable, VerticalAlignment};
 use pixelfield::pixelfield::{PixelField, Point};
 use std::future::Future;
@@ -47,6 +47,14 @@ impl Renderable for Canvas {
             Some(pixel_field)
         })
     }
+
+    fn subscribe(&self, state_manager: &ModelManager) -> ModelSubscription {
+        let mut subscription = ModelSubscription::default();
+        for component in &self.components {
+            subscription.merge(component.renderable.subscribe(state_manager));
+        }
+        subscription
+    }
 }
 
 pub struct Component {
@@ -1,4 +1,4 @@
-use crate::model::ModelManager;
+use crate::model::{ModelManager, ModelSubscription};
 use pixelfield::pixelfield::PixelField;
 use std::future::Future;
 use std::pin::Pin;
@@ -27,4 +27,9 @@ pub trait Renderable: Send + Sync {
         &'r self,
         state_manager: &'r ModelManager,
     ) -> Pin<Box<dyn Future<Output = Option<PixelField>> + 'r>>;
+
+    /// Changes to the models this renderable reads from.
+    fn subscribe(&self, _state_manager: &ModelManager) -> ModelSubscription {
+        ModelSubscription::default()
+    }
 }
@@ -1,4 +1,4 @@
-use crate::model::ModelManager;
+use crate::model::{ModelManager, ModelSubscription};
 use crate::view::Renderable;
 use pixelfield::pixelfield::{PixelField, Rotation};
 use std::future::Future;
@@ -27,4 +27,8 @@ impl<R: Renderable> Renderable for Rotate<R> {
                 .map(|inner| inner.rotate(self.rotation))
         })
     }
+
+    fn subscribe(&self, state_manager: &ModelManager) -> ModelSubscription {
+        self.inner.subscribe(state_manager)
+    }
 }
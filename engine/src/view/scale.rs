@@ -1,4 +1,4 @@
-use crate::model::ModelManager;
+use crate::model::{ModelManager, ModelSubscription};
 use crate::view::Renderable;
 use pixelfield::pixelfield::PixelField;
 use std::future::Future;
@@ -27,4 +27,8 @@ impl<R: Renderable> Renderable for Scale<R> {
                 .map(|inner| inner.scale(self.scale))
         })
     }
+
+    fn subscribe(&self, state_manager: &ModelManager) -> ModelSubscription {
+        self.inner.subscribe(state_manager)
+    }
 }
@@ -1,4 +1,4 @@
-use crate::model::{ModelKey, ModelManager};
+use crate::model::{ModelKey, ModelManager, ModelSubscription};
 use crate::view::Renderable;
 use ab_glyph::{Font, FontRef, PxScale};
 use glyph_brush_layout::{
@@ -111,6 +111,23 @@ impl Renderable for Text {
             }
         })
     }
+
+    fn subscribe(&self, state_manager: &ModelManager) -> ModelSubscription {
+        match &self.source {
+            Source::Managed(key) => state_manager.subscribe_key(key),
+            _ => ModelSubscription::default(),
+        }
+    }
+}
+
+/// What a [`FormattedText`] does with a model past its max-age.
+#[derive(Clone, Debug, Default)]
+pub enum Staleness {
+    #[default]
+    Show,
+    Hide,
+    /// Append the marker to the formatted text.
+    Mark(String),
 }
 
 pub struct FormattedText<Input, FnIn>
@@ -120,6 +137,7 @@ where
 {
     formatter: Box<dyn Fn(FnIn) -> Option<String> + Send + Sync>,
     input_state: ModelKey<Input>,
+    staleness: Staleness,
     output_state: Arc<Mutex<Option<String>>>,
     text: Text,
 }
@@ -140,10 +158,16 @@ where
         Self {
             formatter: Box::new(formatter),
             input_state: state.clone(),
+            staleness: Staleness::default(),
             output_state: output_state.clone(),
             text: Text::new(width, font, size, Source::Dynamic(output_state.clone())),
         }
     }
+
+    pub fn staleness(mut self, staleness: Staleness) -> Self {
+        self.staleness = staleness;
+        self
+    }
 }
 
 impl<Input, FnIn> Renderable for FormattedText<Input, FnIn>
@@ -157,9 +181,23 @@ where
     ) -> Pin<Box<dyn Future<Output = Option<PixelField>> + 'r>> {
         Box::pin(async move {
             //if let Some(locked) = &*self.input_state.lock().await {
-            if let Some(value) = state_manager.get(&self.input_state).await {
+            let Some(model) = state_manager.get_with_metadata(&self.input_state).await else {
+                println!("no data");
+                return None;
+            };
+            let stale = model.metadata.is_stale();
+            if stale && matches!(self.staleness, Staleness::Hide) {
+                return None;
+            }
+
+            if let Some(value) = model.value {
                 println!("some model");
-                let s = (self.formatter)(value.clone().into());
+                let mut s = (self.formatter)(value.into());
+                if let (Staleness::Mark(marker), Some(s)) = (&self.staleness, &mut s) {
+                    if stale {
+                        s.push_str(marker);
+                    }
+                }
                 println!("formatted {:?}", s);
                 *self.output_state.lock().await = s;
                 println!("render inner");
@@ -170,4 +208,8 @@ where
             }
         })
     }
+
+    fn subscribe(&self, state_manager: &ModelManager) -> ModelSubscription {
+        state_manager.subscribe_key(&self.input_state)
+    }
 }
@@ -1,4 +1,4 @@
-use crate::model::ModelManager;
+use crate::model::{ModelManager, ModelSubscription};
 use crate::view::Renderable;
 use pixelfield::pixelfield::PixelField;
 use std::future::Future;
@@ -26,4 +26,8 @@ impl<R: Renderable> Renderable for Trim<R> {
                 .map(|inner| inner.trim())
         })
     }
+
+    fn subscribe(&self, state_manager: &ModelManager) -> ModelSubscription {
+        self.inner.subscribe(state_manager)
+    }
 }
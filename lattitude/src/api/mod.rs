@@ -0,0 +1,339 @@
+use crate::coordinator::{DisplayPage, Interaction};
+use crate::page::LattitudePage;
+use crate::{HEIGHT, WIDTH};
+use chrono::{DateTime, Utc};
+use engine::display::png::FrameDisplay;
+use engine::engine::health::IntegrationHealth;
+use engine::engine::Engine;
+use engine::metrics;
+use engine::model::ModelDescription;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+/// Local HTTP API for managing a frame on the wall:
+///
+/// - `GET /integrations`: health and cadence of every controller
+/// - `GET /models`: every model's current value
+/// - `GET /metrics`: Prometheus metrics
+/// - `POST /refresh`, `POST /integrations/<key>/refresh[?controller=<name>]`
+/// - `POST /interactions/push/<page>`, `/interactions/pop`, `/interactions/clear`
+/// - `GET /frame.png`: the most recently rendered frame
+pub struct Api {
+    engine: Arc<Engine>,
+    interactions: Option<Sender<Interaction<LattitudePage>>>,
+    frame: Option<FrameDisplay<WIDTH, HEIGHT>>,
+}
+
+impl Api {
+    pub fn new(engine: Arc<Engine>) -> Self {
+        Self {
+            engine,
+            interactions: None,
+            frame: None,
+        }
+    }
+
+    /// Where to send page interactions, i.e. the coordinator.
+    pub fn set_interactions(&mut self, interactions: Sender<Interaction<LattitudePage>>) {
+        self.interactions.replace(interactions);
+    }
+
+    /// The display holding the frame served at `/frame.png`.
+    pub fn set_frame(&mut self, frame: FrameDisplay<WIDTH, HEIGHT>) {
+        self.frame.replace(frame);
+    }
+
+    pub async fn serve(self, address: SocketAddr) -> Result<(), hyper::Error> {
+        let api = Arc::new(self);
+        let make_service = make_service_fn(move |_| {
+            let api = api.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let api = api.clone();
+                    async move { Ok::<_, Infallible>(api.handle(request).await) }
+                }))
+            }
+        });
+        Server::try_bind(&address)?.serve(make_service).await
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let path: Vec<_> = request
+            .uri()
+            .path()
+            .split('/')
+            .filter(|inner| !inner.is_empty())
+            .collect();
+
+        match (request.method(), path.as_slice()) {
+            (&Method::GET, ["integrations"]) => {
+                let health = self.engine.health().await;
+                json(&health.iter().map(IntegrationView::from).collect::<Vec<_>>())
+            }
+            (&Method::GET, ["models"]) => {
+                let models = self.engine.model_manager().describe().await;
+                json(&models.iter().map(ModelView::from).collect::<Vec<_>>())
+            }
+            (&Method::GET, ["metrics"]) => Response::builder()
+                .header(CONTENT_TYPE, metrics::CONTENT_TYPE)
+                .body(Body::from(self.engine.metrics().encode()))
+                .unwrap(),
+            (&Method::POST, ["refresh"]) => {
+                self.engine.handle().refresh_all();
+                status(StatusCode::ACCEPTED, "refreshing")
+            }
+            (&Method::POST, ["integrations", key, "refresh"]) => {
+                let known = self.engine.health().await.iter().any(|inner| {
+                    inner.key == *key
+                        || inner
+                            .key
+                            .strip_prefix(key)
+                            .map_or(false, |instance| instance.starts_with('.'))
+                });
+                if !known {
+                    return status(StatusCode::NOT_FOUND, "no such integration");
+                }
+                let controller = query(&request, "controller");
+                self.engine.handle().refresh(key, controller.as_deref());
+                status(StatusCode::ACCEPTED, "refreshing")
+            }
+            (&Method::POST, ["interactions", "push", page]) => match page.parse() {
+                Ok(page) => {
+                    self.interact(Interaction::Push(DisplayPage::PageRef(page)))
+                        .await
+                }
+                Err(err) => status(StatusCode::NOT_FOUND, &err),
+            },
+            (&Method::POST, ["interactions", "pop"]) => self.interact(Interaction::Pop).await,
+            (&Method::POST, ["interactions", "clear"]) => self.interact(Interaction::Clear).await,
+            (&Method::GET, ["frame.png"]) => {
+                match self.frame.as_ref().and_then(|inner| inner.png()) {
+                    Some(png) => Response::builder()
+                        .header(CONTENT_TYPE, "image/png")
+                        .body(Body::from(png))
+                        .unwrap(),
+                    None => status(StatusCode::NOT_FOUND, "nothing rendered yet"),
+                }
+            }
+            _ => status(StatusCode::NOT_FOUND, "not found"),
+        }
+    }
+
+    async fn interact(&self, interaction: Interaction<LattitudePage>) -> Response<Body> {
+        let Some(interactions) = &self.interactions else {
+            return status(StatusCode::SERVICE_UNAVAILABLE, "no display");
+        };
+        match interactions.send(interaction).await {
+            Ok(()) => status(StatusCode::ACCEPTED, "sent"),
+            Err(_) => status(StatusCode::SERVICE_UNAVAILABLE, "display stopped"),
+        }
+    }
+}
+
+/// The percent-decoded value of the `name` query parameter.
+fn query(request: &Request<Body>, name: &str) -> Option<String> {
+    form_urlencoded::parse(request.uri().query()?.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+fn json<T: Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_vec_pretty(value) {
+        Ok(body) => Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap(),
+        Err(err) => status(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+    }
+}
+
+fn status(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain")
+        .body(Body::from(format!("{}\n", message)))
+        .unwrap()
+}
+
+#[derive(Serialize)]
+struct IntegrationView {
+    key: String,
+    name: String,
+    enabled: bool,
+    healthy: bool,
+    controllers: Vec<ControllerView>,
+}
+
+#[derive(Serialize)]
+struct ControllerView {
+    controller: String,
+    cadence_seconds: Option<i64>,
+    next_update: Option<DateTime<Utc>>,
+    last_success: Option<DateTime<Utc>>,
+    last_failure: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+}
+
+impl From<&IntegrationHealth> for IntegrationView {
+    fn from(health: &IntegrationHealth) -> Self {
+        Self {
+            key: health.key.clone(),
+            name: health.name.clone(),
+            enabled: health.enabled,
+            healthy: health.is_healthy(),
+            controllers: health
+                .controllers
+                .iter()
+                .map(|inner| ControllerView {
+                    controller: inner.controller.clone(),
+                    cadence_seconds: inner.cadence.map(|cadence| cadence.num_seconds()),
+                    next_update: inner.next_update,
+                    last_success: inner.health.last_success,
+                    last_failure: inner.health.last_failure,
+                    last_error: inner.health.last_error.clone(),
+                    consecutive_failures: inner.health.consecutive_failures,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ModelView {
+    provider: String,
+    #[serde(rename = "type")]
+    type_name: &'static str,
+    value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json: Option<serde_json::Value>,
+    provides: Vec<&'static str>,
+    updated_at: Option<DateTime<Utc>>,
+    stale: bool,
+}
+
+impl From<&ModelDescription> for ModelView {
+    fn from(description: &ModelDescription) -> Self {
+        Self {
+            provider: description.metadata.provider.clone(),
+            type_name: description.type_name,
+            value: description.value.clone(),
+            json: description.json.clone(),
+            provides: description.provides.clone(),
+            updated_at: description.metadata.updated_at,
+            stale: description.metadata.is_stale(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use engine::display::Display;
+    use hyper::body::to_bytes;
+    use pixelfield::pixelfield::PixelField;
+    use tokio::sync::mpsc::channel;
+
+    async fn call(api: &Api, method: Method, uri: &str) -> (StatusCode, Vec<u8>) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = api.handle(request).await;
+        let status = response.status();
+        (
+            status,
+            to_bytes(response.into_body()).await.unwrap().to_vec(),
+        )
+    }
+
+    #[tokio::test]
+    async fn inspect_and_refresh() {
+        let api = Api::new(Arc::new(Engine::new()));
+
+        let (status, body) = call(&api, Method::GET, "/integrations").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"[]");
+        assert_eq!(call(&api, Method::GET, "/models").await.0, StatusCode::OK);
+
+        assert_eq!(
+            call(&api, Method::POST, "/refresh").await.0,
+            StatusCode::ACCEPTED
+        );
+        let (status, _) = call(&api, Method::POST, "/integrations/birdnet/refresh").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(
+            call(&api, Method::GET, "/refresh").await.0,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn metrics() {
+        let engine = Engine::new();
+        engine
+            .metrics()
+            .record_render("Splash", std::time::Duration::from_millis(40));
+        let api = Api::new(Arc::new(engine));
+
+        let (status, body) = call(&api, Method::GET, "/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.lines().any(
+            |inner| inner == "lattitude_page_render_duration_seconds_count{page=\"Splash\"} 1"
+        ));
+    }
+
+    #[test]
+    fn query_values_are_decoded() {
+        let request = Request::builder()
+            .uri("/integrations/accuweather/refresh?a=1&controller=Daily%20Forecast&b")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            query(&request, "controller").as_deref(),
+            Some("Daily Forecast")
+        );
+        assert_eq!(query(&request, "b").as_deref(), Some(""));
+        assert_eq!(query(&request, "c"), None);
+    }
+
+    #[tokio::test]
+    async fn interactions_and_frame() {
+        let mut api = Api::new(Arc::new(Engine::new()));
+        let (status, _) = call(&api, Method::POST, "/interactions/pop").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        let (sender, mut receiver) = channel(1);
+        api.set_interactions(sender);
+        let frame = FrameDisplay::new();
+        api.set_frame(frame.clone());
+
+        let (status, _) = call(&api, Method::POST, "/interactions/push/Splash").await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert!(matches!(
+            receiver.recv().await,
+            Some(Interaction::Push(DisplayPage::PageRef(
+                LattitudePage::Splash
+            )))
+        ));
+        let (status, _) = call(&api, Method::POST, "/interactions/push/nowhere").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        assert_eq!(
+            call(&api, Method::GET, "/frame.png").await.0,
+            StatusCode::NOT_FOUND
+        );
+        frame.clone().display(&PixelField::default());
+        let (status, body) = call(&api, Method::GET, "/frame.png").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with(b"\x89PNG"));
+    }
+}
@@ -1,5 +1,7 @@
 mod calibrate;
 mod clear;
+mod models;
+mod refresh;
 mod run;
 mod splash;
 mod unbox;
@@ -8,6 +10,8 @@ use crate::cli::splash::SplashCommand;
 use calibrate::CalibrateCommand;
 use clap::{Args, Parser, Subcommand};
 use clear::ClearCommand;
+use models::ModelsCommand;
+use refresh::RefreshCommand;
 use run::RunCommand;
 use unbox::UnboxCommand;
 
@@ -30,16 +34,21 @@ pub enum Command {
     Splash(SplashCommand),
     Run(RunCommand),
     Calibrate(CalibrateCommand),
+    Models(ModelsCommand),
+    Refresh(RefreshCommand),
 }
 
 impl Command {
-    pub async fn run(&self) {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
         match self {
             Command::Clear(inner) => inner.run().await,
             Command::Unbox(inner) => inner.run().await,
             Command::Splash(inner) => inner.run().await,
-            Command::Run(inner) => inner.run().await,
+            Command::Run(inner) => return inner.run().await,
             Command::Calibrate(inner) => inner.run().await,
+            Command::Models(inner) => inner.run().await,
+            Command::Refresh(inner) => return inner.run().await,
         }
+        Ok(())
     }
 }
@@ -0,0 +1,164 @@
+use crate::integration::{register_derivations, register_factories};
+use chrono::{DateTime, Utc};
+use clap::Args;
+use engine::engine::Engine;
+use engine::model::ModelDescription;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[derive(Args, Debug, Clone)]
+#[command(
+    about = "List models, their providers and conversions",
+    args_conflicts_with_subcommands = true
+)]
+pub struct ModelsCommand {
+    /// Directory of `<integration>.toml` configuration files
+    #[arg(short, long, default_value = "/etc/lattitude")]
+    config: PathBuf,
+
+    /// Directory of model snapshots, read for `--values` when no running
+    /// instance answers
+    #[arg(long, default_value = "/var/lib/lattitude")]
+    state: PathBuf,
+
+    /// Also print each model's value, as JSON for serializable models
+    #[arg(long)]
+    values: bool,
+
+    /// Address of the running instance's API, as passed to `run --api`, to
+    /// read current values from
+    #[arg(long, value_name = "ADDRESS")]
+    api: Option<SocketAddr>,
+}
+
+/// A model as the running instance's `GET /models` reports it.
+#[derive(Deserialize)]
+struct LiveModel {
+    provider: String,
+    #[serde(rename = "type")]
+    type_name: String,
+    value: Option<String>,
+    json: Option<serde_json::Value>,
+    updated_at: Option<DateTime<Utc>>,
+    stale: bool,
+}
+
+impl ModelsCommand {
+    pub async fn run(&self) {
+        // the same models `run` registers, without starting any integration
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&self.config);
+        engine.set_state_directory(&self.state);
+        register_factories(&mut engine);
+        if engine.instantiate().is_empty() {
+            log::warn!("no integration configured in {}", self.config.display());
+        }
+        register_derivations(&mut engine);
+
+        let live = match (self.values, self.api) {
+            (true, Some(address)) => self.fetch(address).await,
+            _ => None,
+        };
+        if self.values && live.is_none() {
+            println!(
+                "(offline: values from the snapshots in {})",
+                self.state.display()
+            );
+        }
+
+        let models = engine.model_manager();
+        models.recompute().await;
+        let mut provider = None;
+        for description in &models.describe().await {
+            if provider != Some(&description.metadata.provider) {
+                provider = Some(&description.metadata.provider);
+                println!("{}", description.metadata.provider);
+            }
+            self.print(description, live.as_deref());
+        }
+
+        let conversions = models.conversions();
+        if !conversions.is_empty() {
+            println!();
+            println!("conversions");
+            for conversion in conversions {
+                let fallible = if conversion.fallible {
+                    " (fallible)"
+                } else {
+                    ""
+                };
+                println!("  {} -> {}{}", conversion.from, conversion.to, fallible);
+            }
+        }
+    }
+
+    /// Current values from the running instance, or `None` if it cannot be
+    /// reached.
+    async fn fetch(&self, address: SocketAddr) -> Option<Vec<LiveModel>> {
+        let response = reqwest::get(format!("http://{}/models", address))
+            .await
+            .and_then(|response| response.error_for_status());
+        let models = match response {
+            Ok(response) => response.json().await,
+            Err(err) => Err(err),
+        };
+        match models {
+            Ok(models) => Some(models),
+            Err(err) => {
+                log::warn!("cannot read values from {}: {}", address, err);
+                None
+            }
+        }
+    }
+
+    fn print(&self, description: &ModelDescription, live: Option<&[LiveModel]>) {
+        let metadata = &description.metadata;
+        let found = live.and_then(|live| {
+            live.iter().find(|inner| {
+                inner.provider == metadata.provider && inner.type_name == description.type_name
+            })
+        });
+        // a model the running instance lacks, e.g. configured since, falls
+        // back to its snapshot
+        let snapshot = if live.is_some() && found.is_none() {
+            ", from snapshot"
+        } else {
+            ""
+        };
+        let live = found;
+
+        let (updated_at, stale) = match live {
+            Some(live) => (live.updated_at, live.stale),
+            None => (metadata.updated_at, metadata.is_stale()),
+        };
+        let updated = match updated_at {
+            Some(updated_at) => format!("updated {}", updated_at.to_rfc3339()),
+            None => "never updated".to_string(),
+        };
+        let stale = if stale { ", stale" } else { "" };
+        println!(
+            "  {} ({}{}{})",
+            description.type_name, updated, stale, snapshot
+        );
+
+        for provided in &description.provides {
+            println!("    -> {}", provided);
+        }
+
+        if self.values {
+            let (json, value) = match live {
+                Some(live) => (&live.json, &live.value),
+                None => (&description.json, &description.value),
+            };
+            let value = match (json, value) {
+                (Some(json), _) => serde_json::to_string_pretty(json).unwrap_or_default(),
+                (None, Some(value)) => value.clone(),
+                (None, None) => "none".to_string(),
+            };
+            for line in value.lines() {
+                println!("    | {}", line);
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use anyhow::{bail, Context};
+use clap::Args;
+use std::net::SocketAddr;
+
+#[derive(Args, Debug, Clone)]
+#[command(
+    about = "Update an integration's controllers now, regardless of schedule",
+    args_conflicts_with_subcommands = true
+)]
+pub struct RefreshCommand {
+    /// Address of the running instance's API, as passed to `run --api`
+    #[arg(long, value_name = "ADDRESS")]
+    api: SocketAddr,
+
+    /// Integration key, or `<key>.<name>` for a named instance
+    integration: String,
+
+    /// Only refresh this controller, e.g. `Hourly`
+    controller: Option<String>,
+}
+
+impl RefreshCommand {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        let url = format!(
+            "http://{}/integrations/{}/refresh",
+            self.api, self.integration
+        );
+        let mut request = reqwest::Client::new().post(&url);
+        if let Some(controller) = &self.controller {
+            request = request.query(&[("controller", controller)]);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("unable to reach the API at {}", self.api))?;
+
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            bail!("{}: {}", self.integration, message.trim());
+        }
+        println!("{}", message.trim());
+        Ok(())
+    }
+}
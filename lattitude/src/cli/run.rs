@@ -1,9 +1,167 @@
+use crate::api::Api;
+use crate::art::build_art_registry;
+use crate::coordinator::Coordinator;
+use crate::font::build_font_registry;
+use crate::integration::{register_derivations, register_factories};
+use crate::page::{build_page_manager, LattitudePage};
+use crate::{HEIGHT, WIDTH};
+use anyhow::Context;
 use clap::Args;
+use engine::display::bmp::BmpDisplay;
+use engine::display::png::FrameDisplay;
+use engine::engine::Engine;
+use std::future::pending;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
+
+/// `EX_CONFIG` from sysexits(3).
+const EXIT_CONFIGURATION: i32 = 78;
 
 #[derive(Args, Debug, Clone)]
 #[command(about = "Run Låttitüdé", args_conflicts_with_subcommands = true)]
-pub struct RunCommand {}
+pub struct RunCommand {
+    /// Directory of `<integration>.toml` configuration files
+    #[arg(short, long, default_value = "/etc/lattitude")]
+    config: PathBuf,
+
+    /// Directory in which model snapshots survive restarts
+    #[arg(long, default_value = "/var/lib/lattitude")]
+    state: PathBuf,
+
+    /// Exit when any configuration file fails to load
+    #[arg(long)]
+    strict: bool,
+
+    /// Render the display to a BMP file, redrawn whenever its data changes
+    #[arg(long)]
+    bmp: Option<PathBuf>,
+
+    /// Write Prometheus metrics to a file after every round of updates
+    #[arg(long, value_name = "PATH")]
+    metrics_file: Option<PathBuf>,
+
+    /// Serve the control and inspection API, including Prometheus metrics at
+    /// `/metrics`, at `http://<ADDRESS>/`
+    #[arg(long, value_name = "ADDRESS")]
+    api: Option<SocketAddr>,
+}
 
 impl RunCommand {
-    pub async fn run(&self) {}
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        let mut engine = Engine::new();
+        engine.set_configuration_directory(&self.config);
+        engine.set_strict(self.strict);
+        engine.set_state_directory(&self.state);
+        if let Some(path) = &self.metrics_file {
+            engine.set_metrics_file(path);
+        }
+        register_factories(&mut engine);
+        if engine.instantiate().is_empty() {
+            log::warn!("no integration configured in {}", self.config.display());
+        }
+        register_derivations(&mut engine);
+
+        let engine = Arc::new(engine);
+        let mut api = self.api.map(|_| Api::new(engine.clone()));
+
+        let mut coordinator = None;
+        if self.bmp.is_some() || api.is_some() {
+            let font = build_font_registry().context("unable to load the fonts")?;
+            let art = build_art_registry().context("unable to load the art")?;
+            let mut inner = Coordinator::new(build_page_manager::<WIDTH, HEIGHT>(
+                &font,
+                &art,
+                engine.model_manager(),
+            ));
+            if let Some(path) = &self.bmp {
+                inner.add_display(BmpDisplay::<WIDTH, HEIGHT>::new(path.clone()));
+            }
+            if let Some(api) = &mut api {
+                let frame = FrameDisplay::new();
+                inner.add_display(frame.clone());
+                api.set_frame(frame);
+                api.set_interactions(inner.sender());
+            }
+            inner.set_engine(engine.handle());
+            inner.set_metrics(engine.metrics());
+            coordinator.replace(inner);
+        }
+
+        let handle = engine.handle();
+        let run = engine.run();
+        tokio::pin!(run);
+
+        // `kill -USR1` refreshes every integration, e.g. after fixing an API key
+        let refresh = {
+            let handle = handle.clone();
+            async move {
+                let Ok(mut signal) = signal(SignalKind::user_defined1()) else {
+                    return pending().await;
+                };
+                while signal.recv().await.is_some() {
+                    log::info!("refreshing all integrations");
+                    handle.refresh_all();
+                }
+                pending().await
+            }
+        };
+
+        let api = async {
+            let (Some(api), Some(address)) = (api, self.api) else {
+                return pending().await;
+            };
+            log::info!("serving the API on http://{}/", address);
+            if let Err(err) = api.serve(address).await {
+                log::error!("cannot serve the API on {}: {}", address, err);
+            }
+            pending().await
+        };
+
+        let display = async {
+            match &mut coordinator {
+                Some(coordinator) => {
+                    coordinator
+                        .run(
+                            engine.model_manager(),
+                            LattitudePage::Splash,
+                            LattitudePage::Splash,
+                        )
+                        .await
+                }
+                None => pending().await,
+            }
+        };
+
+        // systemd and docker stop with SIGTERM rather than SIGINT
+        let terminate = async {
+            match signal(SignalKind::terminate()) {
+                Ok(mut signal) => signal.recv().await,
+                Err(_) => pending().await,
+            }
+        };
+
+        let result = tokio::select! {
+            result = &mut run => result,
+            _ = display => unreachable!("the coordinator never returns"),
+            _ = refresh => unreachable!("the refresh listener never returns"),
+            _ = api => unreachable!("the API server never returns"),
+            _ = tokio::signal::ctrl_c() => {
+                handle.shutdown();
+                run.await
+            }
+            _ = terminate => {
+                handle.shutdown();
+                run.await
+            }
+        };
+
+        // the engine has already logged each error
+        if result.is_err() {
+            process::exit(EXIT_CONFIGURATION);
+        }
+        Ok(())
+    }
 }
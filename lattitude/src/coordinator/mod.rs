@@ -1,28 +1,25 @@
-use crate::display::Display;
-use crate::page::LattitudePage;
-use engine::controller::Controllers;
+use engine::display::Display;
+use engine::engine::handle::{EngineHandle, Refresh};
+use engine::metrics::Metrics;
+use engine::model::ModelManager;
 use engine::page::{Page, PageManager};
-use std::env;
+use std::fmt::Debug;
 use std::hash::Hash;
-use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
-use tokio::sync::{mpsc, Mutex};
-use engine::integration::{Integration, Integrations};
-use engine::model::ModelManager;
+
+/// Redraw at least this often, even if nothing changed, to keep e-paper
+/// ghosting at bay.
+const IDLE_REDRAW: Duration = Duration::from_secs(10 * 60);
+
+const BOOT_SCREEN: Duration = Duration::from_secs(5);
 
 pub enum DisplayPage<PageId> {
     PageRef(PageId),
     Page(Page),
 }
 
-impl From<LattitudePage> for DisplayPage<LattitudePage> {
-    fn from(value: LattitudePage) -> Self {
-        Self::PageRef(value)
-    }
-}
-
-impl From<Page> for DisplayPage<LattitudePage> {
+impl<PageId> From<Page> for DisplayPage<PageId> {
     fn from(value: Page) -> Self {
         Self::Page(value)
     }
@@ -32,78 +29,81 @@ pub struct Coordinator<PageId, const WIDTH: u32, const HEIGHT: u32>
 where
     PageId: Hash + PartialEq + Eq,
 {
-    controllers: Arc<Mutex<Controllers>>,
     page_manager: PageManager<PageId, WIDTH, HEIGHT>,
-    displays: Vec<Box<dyn Display>>,
-    sender: Sender<Interaction>,
-    receiver: Receiver<Interaction>,
+    displays: Vec<Box<dyn Display + Send>>,
+    engine: Option<EngineHandle>,
+    metrics: Metrics,
+    sender: Sender<Interaction<PageId>>,
+    receiver: Receiver<Interaction<PageId>>,
 }
 
 impl<PageId, const WIDTH: u32, const HEIGHT: u32> Coordinator<PageId, WIDTH, HEIGHT>
 where
-    PageId: Copy + Hash + PartialEq + Eq + Send + Sync,
+    PageId: Copy + Debug + Hash + PartialEq + Eq + Send + Sync,
 {
-    pub fn new(controllers: Controllers, page_manager: PageManager<PageId, WIDTH, HEIGHT>) -> Self {
+    pub fn new(page_manager: PageManager<PageId, WIDTH, HEIGHT>) -> Self {
         let (sender, receiver) = channel(12);
 
         Self {
-            controllers: Arc::new(Mutex::new(controllers)),
             page_manager,
             displays: vec![],
+            engine: None,
+            metrics: Default::default(),
             sender,
             receiver,
         }
     }
 
-    pub fn add_display<D: Display + 'static>(&mut self, display: D) {
+    pub fn add_display<D: Display + Send + 'static>(&mut self, display: D) {
         self.displays.push(Box::new(display));
     }
 
-    pub async fn display<'d, D: Into<&'d DisplayPage<PageId>>>(&mut self, state_manager: &ModelManager, display: D)
-    where
-        PageId: 'd,
-    {
-        let display = display.into();
-        let pixels = match display {
-            DisplayPage::PageRef(page_id) => self.page_manager.render(state_manager, *page_id).await,
-            DisplayPage::Page(page) => page.render(state_manager).await,
+    /// The engine to forward [`Interaction::Refresh`] to.
+    pub fn set_engine(&mut self, engine: EngineHandle) {
+        self.engine.replace(engine);
+    }
+
+    /// Record render and display timings in `metrics`, e.g. the engine's.
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = metrics;
+    }
+
+    pub async fn display(&mut self, state_manager: &ModelManager, display: &DisplayPage<PageId>) {
+        let started = Instant::now();
+        let (page, pixels) = match display {
+            DisplayPage::PageRef(page_id) => (
+                format!("{:?}", page_id),
+                self.page_manager.render(state_manager, *page_id).await,
+            ),
+            DisplayPage::Page(page) => ("transient".to_string(), page.render(state_manager).await),
         };
+        self.metrics.record_render(&page, started.elapsed());
 
         for display in self.displays.iter_mut() {
+            let started = Instant::now();
             display.display(&pixels);
+            self.metrics.record_push(display.name(), started.elapsed());
         }
-
-        /*
-        let bmp = pixels.to_bmp();
-
-        let path = env::current_dir().unwrap();
-        let path = path.join("lattitude.bmp");
-        println!("wrote image {:?}", path);
-        bmp.save(path);
-         */
     }
 
-    pub fn sender(&self) -> Sender<Interaction> {
+    pub fn sender(&self) -> Sender<Interaction<PageId>> {
         self.sender.clone()
     }
 
-    pub async fn run(&mut self, state_manager: &ModelManager, initial_page: PageId, home_page: PageId) {
-        let mut page = initial_page;
+    /// Show `initial_page` while booting, then keep the navigation stack on
+    /// screen, redrawing only when a model it renders changes.
+    pub async fn run(
+        &mut self,
+        state_manager: &ModelManager,
+        initial_page: PageId,
+        home_page: PageId,
+    ) {
         let mut navigation_stack: Vec<DisplayPage<PageId>> = Vec::new();
 
-        let controllers = self.controllers.clone();
-
-        let handle = tokio::spawn(async move {
-            loop {
-                println!("running update loop");
-                controllers.lock().await.update().await;
-                tokio::time::sleep(Duration::from_secs(1)).await;
-            }
-        });
-
         // boot screen
-        self.page_manager.render(state_manager, page).await;
-        tokio::time::sleep(Duration::from_secs(5)).await;
+        self.display(state_manager, &DisplayPage::PageRef(initial_page))
+            .await;
+        tokio::time::sleep(BOOT_SCREEN).await;
 
         // regular loop-de-loop
         loop {
@@ -111,18 +111,50 @@ where
                 navigation_stack.push(DisplayPage::PageRef(home_page));
             }
 
+            let mut subscription = match navigation_stack.last() {
+                Some(DisplayPage::PageRef(page_id)) => {
+                    self.page_manager.subscribe(state_manager, *page_id)
+                }
+                Some(DisplayPage::Page(page)) => page.subscribe(state_manager),
+                None => Default::default(),
+            };
+
             if let Some(cur_page) = navigation_stack.last() {
                 self.display(state_manager, cur_page).await;
             }
 
-            // TODO: select() on timeout or interaction channel
-            tokio::time::sleep(Duration::from_secs(60)).await;
+            tokio::select! {
+                _ = subscription.changed() => {}
+                _ = tokio::time::sleep(IDLE_REDRAW) => {}
+                interaction = self.receiver.recv() => match interaction {
+                    Some(Interaction::Push(page)) => navigation_stack.push(page),
+                    Some(Interaction::Pop) => {
+                        navigation_stack.pop();
+                    }
+                    Some(Interaction::Clear) => navigation_stack.clear(),
+                    // the page redraws once the refreshed models change
+                    Some(Interaction::Refresh(refresh)) => {
+                        if let Some(engine) = &self.engine {
+                            match refresh.integration {
+                                Some(integration) => {
+                                    engine.refresh(&integration, refresh.controller.as_deref())
+                                }
+                                None => engine.refresh_all(),
+                            }
+                        }
+                    }
+                    // we hold a sender ourselves, so the channel never closes
+                    None => {}
+                },
+            }
         }
     }
 }
 
-pub enum Interaction {
-    Push(DisplayPage<LattitudePage>),
+pub enum Interaction<PageId> {
+    Push(DisplayPage<PageId>),
     Pop,
     Clear,
+    /// Update controllers now, e.g. from a pull-to-refresh button.
+    Refresh(Refresh),
 }
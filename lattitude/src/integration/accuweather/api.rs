@@ -1,14 +1,14 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 
-#[derive(Debug, Clone, Deserialize, Hash, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Wind {
     pub speed: WindSpeed,
     pub direction: WindDirection,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct WindSpeed {
     pub value: f32,
@@ -20,14 +20,14 @@ impl Hash for WindSpeed {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Hash, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct WindDirection {
     pub degrees: u16,
     pub localized: String,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct TotalLiquid {
     pub value: f32,
@@ -39,7 +39,7 @@ impl Hash for TotalLiquid {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Snow {
     pub value: f32,
@@ -51,7 +51,7 @@ impl Hash for Snow {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Rain {
     pub value: f32,
@@ -63,7 +63,7 @@ impl Hash for Rain {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Ice {
     pub value: f32,
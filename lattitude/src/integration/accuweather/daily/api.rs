@@ -1,15 +1,15 @@
 use crate::integration::accuweather::api::{Ice, Rain, Snow, TotalLiquid, Wind};
 use chrono::{DateTime, Local};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Envelope {
     pub daily_forecasts: Vec<DailyForecast>,
 }
 
-#[derive(Debug, Clone, Deserialize, Hash, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct DailyForecast {
     pub date: DateTime<Local>,
@@ -20,14 +20,14 @@ pub struct DailyForecast {
     pub night: Details,
 }
 
-#[derive(Debug, Clone, Deserialize, Hash, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Sun {
     pub rise: DateTime<Local>,
     pub set: DateTime<Local>,
 }
 
-#[derive(Debug, Clone, Deserialize, Hash, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Moon {
     pub rise: Option<DateTime<Local>>,
@@ -35,14 +35,14 @@ pub struct Moon {
     pub phase: String,
 }
 
-#[derive(Debug, Clone, Deserialize, Hash, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Temperature {
     pub minimum: TempValue,
     pub maximum: TempValue,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct TempValue {
     pub value: f32,
@@ -54,7 +54,7 @@ impl Hash for TempValue {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Hash, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Details {
     pub icon: u8,
@@ -1,12 +1,11 @@
 pub mod api;
 
-use crate::integration::accuweather::daily::api::DailyForecast;
-use crate::integration::accuweather::{AccuWeather, Configuration};
-use chrono::Duration;
+use crate::integration::accuweather::daily::api::{DailyForecast, Envelope};
+use crate::integration::accuweather::{Configuration, BASE_URL};
+use engine::global_configuration::GlobalConfiguration;
+use engine::integration::UpdateError;
 use engine::model::Model;
-use std::future::Future;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use reqwest::Client;
 
 pub struct Daily {
     model: Model<Vec<DailyForecast>>,
@@ -25,7 +24,21 @@ impl Daily {
 }
 
 impl Daily {
-    pub fn update(&mut self, config: &Configuration) -> impl Future<Output = ()> + Send + Sync {
-        async move { todo!() }
+    pub async fn update(
+        &mut self,
+        global_configuration: &GlobalConfiguration,
+        config: &Configuration,
+    ) -> Result<(), UpdateError> {
+        let envelope = Client::new()
+            .get(format!("{}/daily/5day/{}", BASE_URL, config.location_key))
+            .query(&config.query(global_configuration))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Envelope>()
+            .await?;
+
+        self.model.update(envelope.daily_forecasts).await;
+        Ok(())
     }
 }
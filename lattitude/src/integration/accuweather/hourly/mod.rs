@@ -1,13 +1,11 @@
 pub mod api;
 
-use crate::integration::accuweather::daily::api::DailyForecast;
-use crate::integration::accuweather::hourly::api::HourlyForecast;
-use crate::integration::accuweather::AccuWeather;
-use chrono::Duration;
+use crate::integration::accuweather::hourly::api::{Envelope, HourlyForecast};
+use crate::integration::accuweather::{Configuration, BASE_URL};
+use engine::global_configuration::GlobalConfiguration;
+use engine::integration::UpdateError;
 use engine::model::Model;
-use std::future::Future;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use reqwest::Client;
 
 pub struct Hourly {
     model: Model<Vec<HourlyForecast>>,
@@ -26,7 +24,24 @@ impl Hourly {
 }
 
 impl Hourly {
-    fn update(&mut self) -> impl Future<Output = ()> + Send + Sync {
-        async move { todo!() }
+    pub async fn update(
+        &mut self,
+        global_configuration: &GlobalConfiguration,
+        config: &Configuration,
+    ) -> Result<(), UpdateError> {
+        let envelope = Client::new()
+            .get(format!(
+                "{}/hourly/12hour/{}",
+                BASE_URL, config.location_key
+            ))
+            .query(&config.query(global_configuration))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Envelope>()
+            .await?;
+
+        self.model.update(envelope.0).await;
+        Ok(())
     }
 }
@@ -2,14 +2,17 @@ pub mod api;
 mod daily;
 mod hourly;
 
-use crate::integration::accuweather::daily::api::DailyForecast;
+pub use daily::api::DailyForecast;
+
 use crate::integration::accuweather::daily::Daily;
 use crate::integration::accuweather::hourly::api::HourlyForecast;
 use crate::integration::accuweather::hourly::Hourly;
 use chrono::Duration;
+use engine::configuration::secret::Secret;
 use engine::engine::integrations::IntegrationContext;
-use engine::global_configuration::GlobalConfiguration;
-use engine::integration::{Integration, IntegrationInfo};
+use engine::global_configuration::{GlobalConfiguration, Units};
+use engine::integration::{Integration, IntegrationInfo, UpdateError};
+use engine::model::Retention;
 use serde::{Deserialize, Serialize};
 use std::future::Future;
 
@@ -22,7 +25,25 @@ pub struct AccuWeather {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Configuration {
-    api_key: String,
+    api_key: Secret<String>,
+    location_key: String,
+}
+
+const BASE_URL: &str = "https://dataservice.accuweather.com/forecasts/v1";
+
+impl Configuration {
+    /// Query parameters shared by every forecast request.
+    fn query(&self, global_configuration: &GlobalConfiguration) -> [(&'static str, String); 4] {
+        [
+            ("apikey", self.api_key.expose().clone()),
+            ("language", global_configuration.locale.clone()),
+            ("details", "true".to_string()),
+            (
+                "metric",
+                (global_configuration.units == Units::Metric).to_string(),
+            ),
+        ]
+    }
 }
 
 #[derive(Hash, PartialEq, Eq, Debug, Copy, Clone)]
@@ -31,6 +52,23 @@ pub enum Controllers {
     Hourly,
 }
 
+impl AccuWeather {
+    pub fn new() -> Self {
+        Self {
+            global_configuration: Default::default(),
+            configuration: None,
+            hourly: Hourly::new(),
+            daily: Daily::new(),
+        }
+    }
+}
+
+impl Default for AccuWeather {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Integration for AccuWeather {
     type Discriminant = Controllers;
     type Configuration = Configuration;
@@ -39,15 +77,22 @@ impl Integration for AccuWeather {
         IntegrationInfo::new("accuweather", "AccuWeather")
     }
 
-    fn integrate(&self, context: &mut IntegrationContext<Self>)
+    fn integrate(&mut self, context: &mut IntegrationContext<Self>)
     where
         Self: Sized,
     {
         context.register_controller(Controllers::Hourly, Duration::minutes(10));
-        context.register_model::<Vec<HourlyForecast>>(self.hourly.model());
+        context
+            .register_model::<Vec<HourlyForecast>>(self.hourly.model())
+            .max_age(Duration::hours(2))
+            .history(Retention::Within(Duration::hours(24)))
+            .persist("hourly");
 
         context.register_controller(Controllers::Daily, Duration::minutes(60));
-        context.register_model::<Vec<DailyForecast>>(self.daily.model());
+        context
+            .register_model::<Vec<DailyForecast>>(self.daily.model())
+            .max_age(Duration::hours(12))
+            .persist("daily");
     }
 
     async fn configure(
@@ -59,16 +104,13 @@ impl Integration for AccuWeather {
         self.configuration = integration_configuration;
     }
 
-    async fn update(&mut self, discriminant: Self::Discriminant) {
-        if let Some(config) = &self.configuration {
-            match discriminant {
-                Controllers::Daily => {
-                    self.daily.update(config);
-                }
-                Controllers::Hourly => {
-                    self.daily.update(config);
-                }
-            }
+    async fn update(&mut self, discriminant: Self::Discriminant) -> Result<(), UpdateError> {
+        let Some(config) = &self.configuration else {
+            return Ok(());
+        };
+        match discriminant {
+            Controllers::Daily => self.daily.update(&self.global_configuration, config).await,
+            Controllers::Hourly => self.hourly.update(&self.global_configuration, config).await,
         }
     }
 }
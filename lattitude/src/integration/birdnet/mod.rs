@@ -1,19 +1,23 @@
 mod api;
 
+use crate::integration::accuweather::DailyForecast;
 use ab_glyph::FontRef;
 use actix::Message;
 use chrono::{DateTime, Duration, Timelike, Utc};
+use chrono_tz::Tz;
+use engine::clock::{SharedClock, SystemClock};
+use engine::configuration::secret::Secret;
 use engine::engine::integrations::IntegrationContext;
 use engine::global_configuration::GlobalConfiguration;
-use engine::integration::{Integration, IntegrationInfo};
-use engine::model::{ModelKey, ModelManager};
+use engine::integration::{Integration, IntegrationInfo, UpdateError};
+use engine::model::{Model, ModelKey, ModelManager, ModelSubscription};
 use engine::view::canvas::Canvas;
 use engine::view::text::FormattedText;
 use engine::view::Renderable;
 use pixelfield::pixelfield::PixelField;
 use reqwest::{blocking, Client};
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -26,21 +30,41 @@ pub enum BirdNetControllers {
     RecentDetections,
 }
 
-pub struct BirdNet {}
+#[derive(Default)]
+pub struct BirdNet {
+    recent_detections: BirdNetRecentDetections,
+}
+
+impl BirdNet {
+    pub fn new() -> Self {
+        Self {
+            recent_detections: BirdNetRecentDetections::new(),
+        }
+    }
+}
 
 impl Integration for BirdNet {
-    type Discriminant = ();
-    type Configuration = ();
+    type Discriminant = BirdNetControllers;
+    type Configuration = Configuration;
 
     fn info() -> IntegrationInfo {
-        todo!()
+        IntegrationInfo::new("birdnet", "BirdNET")
     }
 
-    fn integrate(&self, context: &mut IntegrationContext<Self>)
+    fn integrate(&mut self, context: &mut IntegrationContext<Self>)
     where
         Self: Sized,
     {
-        todo!()
+        self.recent_detections.set_clock(context.clock());
+        context.register_controller(BirdNetControllers::RecentDetections, Duration::minutes(5));
+        context
+            .register_model(self.recent_detections.model())
+            .max_age(Duration::hours(1))
+            .persist("recent-detections");
+        context
+            .register_model(self.recent_detections.log())
+            .max_age(Duration::hours(1))
+            .persist("detection-log");
     }
 
     async fn configure(
@@ -48,51 +72,108 @@ impl Integration for BirdNet {
         global_configuration: GlobalConfiguration,
         integration_configuration: Option<Self::Configuration>,
     ) {
-        todo!()
+        self.recent_detections.configure(integration_configuration);
     }
 
-    async fn update(&mut self, discriminant: Self::Discriminant) {}
+    async fn update(&mut self, discriminant: Self::Discriminant) -> Result<(), UpdateError> {
+        match discriminant {
+            BirdNetControllers::RecentDetections => self.recent_detections.update().await,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Configuration {
-    pub token: String,
+    pub token: Secret<String>,
     pub keep: usize,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct RecentDetections {
     pub detections: Vec<api::Detection>,
 }
 
-#[derive(Default)]
+/// Every detection of the last day, neither deduplicated nor capped at
+/// `keep`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DetectionLog {
+    pub detections: Vec<api::Detection>,
+}
+
+impl DetectionLog {
+    /// How far back the log reaches.
+    pub fn retention() -> Duration {
+        Duration::days(1)
+    }
+
+    fn append(&mut self, detections: &[api::Detection], now: DateTime<Utc>) {
+        let horizon = now - Self::retention();
+        self.detections
+            .retain(|detection| detection.timestamp >= horizon);
+        self.detections.extend(
+            detections
+                .iter()
+                .filter(|detection| detection.timestamp >= horizon)
+                .cloned(),
+        );
+    }
+}
+
 pub struct BirdNetRecentDetections {
+    clock: SharedClock,
     configuration: Option<Configuration>,
     last_fetch: Option<DateTime<Utc>>,
     detections: VecDeque<api::Detection>,
+    model: Model<RecentDetections>,
+    log: DetectionLog,
+    log_model: Model<DetectionLog>,
+}
+
+impl Default for BirdNetRecentDetections {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BirdNetRecentDetections {
     pub fn new() -> Self {
         Self {
+            clock: Arc::new(SystemClock),
             configuration: None,
             last_fetch: None,
             detections: Default::default(),
+            model: Default::default(),
+            log: Default::default(),
+            log_model: Default::default(),
         }
     }
+
+    pub fn model(&self) -> Model<RecentDetections> {
+        self.model.clone()
+    }
+
+    pub fn log(&self) -> Model<DetectionLog> {
+        self.log_model.clone()
+    }
 }
 
 impl BirdNetRecentDetections {
-    fn configure(&mut self) {
-        //println!("configure! {:?}", configuration);
-        //self.configuration = configuration
+    fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
     }
 
-    async fn update(&mut self) {
+    fn configure(&mut self, configuration: Option<Configuration>) {
+        self.configuration = configuration
+    }
+
+    async fn update(&mut self) -> Result<(), UpdateError> {
         if let Some(configuration) = &self.configuration {
-            println!("query birdnet");
-            if let Ok(response) = Client::new()
-                .get(format!("{}/{}/detections", BASE_URL, configuration.token))
+            let response = Client::new()
+                .get(format!(
+                    "{}/{}/detections",
+                    BASE_URL,
+                    configuration.token.expose()
+                ))
                 .query(&[(
                     "from".to_string(),
                     self.last_fetch
@@ -100,46 +181,90 @@ impl BirdNetRecentDetections {
                         .unwrap_or("".to_string()),
                 )])
                 .send()
-                .await
+                .await?
+                .error_for_status()?;
+
+            let data = response.json::<api::Envelope>().await?;
+            let keep = configuration.keep;
+            self.record(&data.detections, keep).await;
+        }
+
+        Ok(())
+    }
+
+    /// Take in a fetch's detections, as of the clock's now.
+    async fn record(&mut self, fetched: &[api::Detection], keep: usize) {
+        let now = self.clock.now();
+        self.last_fetch.replace(now);
+
+        self.log.append(fetched, now);
+        self.log_model.update(self.log.clone()).await;
+
+        let mut detections = Vec::new();
+
+        for detection in fetched {
+            if !detections
+                .iter()
+                .any(|e: &api::Detection| detection.species == e.species)
             {
-                if let Ok(data) = response.json::<api::Envelope>().await {
-                    let mut detections = Vec::new();
-
-                    for detection in &data.detections {
-                        if !detections
-                            .iter()
-                            .any(|e: &api::Detection| detection.species == e.species)
-                        {
-                            detections.push(detection.clone())
-                        }
-                    }
-
-                    let mut num_short = if detections.len() < configuration.keep {
-                        configuration.keep - detections.len()
-                    } else {
-                        0
-                    };
-
-                    while num_short > 0 {
-                        if let Some(backfill) = self.detections.pop_front() {
-                            detections.push(backfill);
-                            num_short -= 1;
-                        } else {
-                            break;
-                        }
-                    }
-
-                    self.detections = detections.iter().cloned().collect();
-
-                    //return Some(RecentDetections { detections });
-                }
+                detections.push(detection.clone())
+            }
+        }
+
+        let mut num_short = if detections.len() < keep {
+            keep - detections.len()
+        } else {
+            0
+        };
+
+        while num_short > 0 {
+            if let Some(backfill) = self.detections.pop_front() {
+                detections.push(backfill);
+                num_short -= 1;
+            } else {
+                break;
             }
         }
 
-        //None
+        self.detections = detections.iter().cloned().collect();
+        self.model.update(RecentDetections { detections }).await;
     }
 }
 
+/// Distinct species detected since today's sunrise.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BirdsSinceSunrise {
+    pub sunrise: DateTime<Tz>,
+    pub species: usize,
+}
+
+/// Derived from the detection log and the daily forecast's sunrise, as of
+/// `now` in the configured timezone, so the count starts over with the next
+/// day's forecast.
+pub fn birds_since_sunrise(
+    now: DateTime<Tz>,
+    (log, daily): (Option<DetectionLog>, Option<Vec<DailyForecast>>),
+) -> Option<BirdsSinceSunrise> {
+    let timezone = now.timezone();
+    let today = now.date_naive();
+    let sunrise = daily?
+        .into_iter()
+        .find(|forecast| forecast.date.with_timezone(&timezone).date_naive() == today)?
+        .sun
+        .rise
+        .with_timezone(&timezone);
+
+    let species = log?
+        .detections
+        .iter()
+        .filter(|detection| detection.timestamp >= sunrise)
+        .map(|detection| detection.species.scientific_name.clone())
+        .collect::<HashSet<_>>()
+        .len();
+
+    Some(BirdsSinceSunrise { sunrise, species })
+}
+
 pub struct BirdList {
     text: FormattedText<RecentDetections, RecentDetections>,
 }
@@ -176,4 +301,137 @@ impl Renderable for BirdList {
     ) -> Pin<Box<dyn Future<Output = Option<PixelField>> + 'r>> {
         self.text.render(state_manager)
     }
+
+    fn subscribe(&self, state_manager: &ModelManager) -> ModelSubscription {
+        self.text.subscribe(state_manager)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+    use engine::clock::ManualClock;
+    use serde_json::json;
+
+    fn eastern(day: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+        Tz::America__New_York
+            .with_ymd_and_hms(2024, 3, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn forecast(sunrise: DateTime<Tz>) -> DailyForecast {
+        let details = json!({
+            "Icon": 1,
+            "IconPhrase": "Sunny",
+            "ShortPhrase": "Sunny",
+            "LongPhrase": "Sunny",
+            "PrecipitationProbability": 0,
+            "TotalLiquid": { "Value": 0.0 },
+            "Snow": { "Value": 0.0 },
+            "Rain": { "Value": 0.0 },
+            "Ice": { "Value": 0.0 },
+            "Wind": { "Speed": { "Value": 5.0 }, "Direction": { "Degrees": 90, "Localized": "E" } },
+            "WindGust": { "Speed": { "Value": 9.0 }, "Direction": { "Degrees": 90, "Localized": "E" } },
+        });
+        serde_json::from_value(json!({
+            "Date": sunrise,
+            "Sun": { "Rise": sunrise, "Set": sunrise + Duration::hours(12) },
+            "Moon": { "Rise": null, "Set": null, "Phase": "Full" },
+            "Temperature": { "Minimum": { "Value": 2.0 }, "Maximum": { "Value": 11.0 } },
+            "Day": details,
+            "Night": details,
+        }))
+        .unwrap()
+    }
+
+    fn detection(at: DateTime<Tz>, name: &str) -> api::Detection {
+        api::Detection {
+            timestamp: at.with_timezone(&Utc),
+            species: api::Species {
+                common_name: name.to_string(),
+                scientific_name: name.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn counts_species_since_sunrise_in_the_configured_timezone() {
+        let log = DetectionLog {
+            detections: vec![
+                detection(eastern(1, 5, 0), "Troglodytes troglodytes"),
+                detection(eastern(1, 7, 0), "Turdus migratorius"),
+                detection(eastern(1, 9, 0), "Turdus migratorius"),
+                detection(eastern(1, 10, 0), "Cyanocitta cristata"),
+            ],
+        };
+        let daily = vec![forecast(eastern(1, 6, 0)), forecast(eastern(2, 6, 5))];
+        let inputs = || (Some(log.clone()), Some(daily.clone()));
+
+        let first = birds_since_sunrise(eastern(1, 12, 0), inputs()).unwrap();
+        assert_eq!(first.sunrise, eastern(1, 6, 0));
+        assert_eq!(first.species, 2);
+
+        // already the 2nd in UTC, still the 1st in New York
+        let evening = birds_since_sunrise(eastern(1, 22, 0), inputs()).unwrap();
+        assert_eq!(evening.sunrise, eastern(1, 6, 0));
+        assert_eq!(evening.species, 2);
+
+        // after midnight the count starts over, without any new detection
+        let second = birds_since_sunrise(eastern(2, 6, 30), inputs()).unwrap();
+        assert_eq!(second.sunrise, eastern(2, 6, 5));
+        assert_eq!(second.species, 0);
+
+        // no forecast for the day, no count
+        assert!(birds_since_sunrise(eastern(3, 12, 0), inputs()).is_none());
+    }
+
+    #[tokio::test]
+    async fn fetches_are_timed_by_the_engine_clock() {
+        let now = eastern(2, 8, 0).with_timezone(&Utc);
+        let clock = ManualClock::new(now);
+        let mut recent = BirdNetRecentDetections::new();
+        recent.set_clock(Arc::new(clock.clone()));
+
+        let fetched = [
+            detection(eastern(1, 7, 0), "Turdus migratorius"),
+            detection(eastern(2, 7, 0), "Cyanocitta cristata"),
+        ];
+        recent.record(&fetched, 1).await;
+        assert_eq!(recent.last_fetch, Some(now));
+        // a day back from the clock's now, not the system's
+        assert_eq!(recent.log().get().await.unwrap().detections, fetched[1..]);
+
+        clock.advance(Duration::minutes(5));
+        recent.record(&[], 1).await;
+        assert_eq!(recent.last_fetch, Some(now + Duration::minutes(5)));
+    }
+
+    #[test]
+    fn the_log_keeps_every_detection_of_the_last_day() {
+        let species = [
+            "Troglodytes troglodytes",
+            "Turdus migratorius",
+            "Cyanocitta cristata",
+            "Poecile atricapillus",
+            "Cardinalis cardinalis",
+        ];
+        let mut log = DetectionLog::default();
+        log.append(
+            &[detection(eastern(1, 3, 0), "Sitta carolinensis")],
+            eastern(1, 3, 0).with_timezone(&Utc),
+        );
+        let detections = species
+            .iter()
+            .enumerate()
+            .map(|(minute, name)| detection(eastern(2, 7, minute as u32), name))
+            .collect::<Vec<_>>();
+        log.append(&detections, eastern(2, 8, 0).with_timezone(&Utc));
+
+        // more species than `keep` ever holds, and nothing older than a day
+        assert_eq!(log.detections, detections);
+        let daily = vec![forecast(eastern(2, 6, 5))];
+        let count = birds_since_sunrise(eastern(2, 8, 0), (Some(log), Some(daily))).unwrap();
+        assert_eq!(count.species, species.len());
+    }
 }
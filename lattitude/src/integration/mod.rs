@@ -1,2 +1,19 @@
+use crate::integration::accuweather::{AccuWeather, DailyForecast};
+use crate::integration::birdnet::{birds_since_sunrise, BirdNet, DetectionLog};
+use engine::engine::Engine;
+
 pub mod accuweather;
 pub mod birdnet;
+
+/// Make every integration available to [`Engine::instantiate`].
+pub fn register_factories(engine: &mut Engine) {
+    engine.register_factory(AccuWeather::new);
+    engine.register_factory(BirdNet::new);
+}
+
+/// Models computed from those of several integrations.
+pub fn register_derivations(engine: &mut Engine) {
+    engine
+        .derive_with_clock::<(DetectionLog, Vec<DailyForecast>), _, _>(birds_since_sunrise)
+        .serializable();
+}
@@ -17,9 +17,10 @@ use pixelfield::pixelfield::{Rectangle, Rotation};
 use std::env;
 use toml::toml;
 
+mod api;
 mod art;
 mod cli;
-//mod coordinator;
+mod coordinator;
 mod display;
 pub mod font;
 pub mod integration;
@@ -33,38 +34,5 @@ async fn main() -> Result<(), anyhow::Error> {
     env_logger::init();
     let cli = Cli::parse();
 
-    cli.command.run().await;
-    /*
-    let font = build_font_registry()?;
-    let art = build_art_registry()?;
-
-    let mut controllers = Controllers::new();
-    let birds = controllers.register(BirdNet::new());
-
-    let config = toml! {
-        keep = 10
-        token = "ovphnKX4kQhdy7DzzUULLWUc"
-    };
-
-    controllers.configure("birdNET", config.into()).await;
-    controllers.update().await;
-
-    let mut coordinator = Coordinator::new(controllers, build_page_manager::<808, 1404>(&font, &art));
-
-    let path = env::current_dir().unwrap();
-    let path = path.join("lattitude.bmp");
-
-    coordinator.add_display(
-        BmpDisplay::<1404, 1872>::new(path)
-    );
-
-    println!("start coord");
-    coordinator
-        .run(LattitudePage::Splash, LattitudePage::Splash)
-        .await;
-    println!("ended coord");
-
-     */
-
-    Ok(())
+    cli.command.run().await
 }
@@ -4,9 +4,12 @@ use crate::page::splash::splash_page;
 use crate::page::unbox::unbox_page;
 use ab_glyph::FontRef;
 use engine::font::FontRegistry;
+use engine::model::{ModelKey, ModelManager};
 use engine::page::PageManager;
 use engine::view::pixels::Pixels;
 use pixelfield::pixelfield::PixelField;
+use std::fmt::Debug;
+use std::str::FromStr;
 
 pub mod splash;
 pub mod unbox;
@@ -14,11 +17,16 @@ pub mod unbox;
 pub struct PageContext<'p> {
     font: &'p FontRegistry<Font>,
     art: &'p ArtRegistry,
+    models: &'p ModelManager,
 }
 
 impl<'p> PageContext<'p> {
-    pub fn new(font: &'p FontRegistry<Font>, art: &'p ArtRegistry) -> Self {
-        Self { font, art }
+    pub fn new(
+        font: &'p FontRegistry<Font>,
+        art: &'p ArtRegistry,
+        models: &'p ModelManager,
+    ) -> Self {
+        Self { font, art, models }
     }
 
     pub fn font(&self, id: Font) -> FontRef<'static> {
@@ -28,24 +36,84 @@ impl<'p> PageContext<'p> {
     pub fn art(&self, id: Art) -> Pixels {
         self.art.get(id)
     }
+
+    /// The key of `provider`'s `T`, if that integration is registered.
+    pub fn model<T: Debug + Clone + 'static>(&self, provider: &str) -> Option<ModelKey<T>> {
+        self.models.key_for(provider)
+    }
 }
 
-#[derive(Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum LattitudePage {
     Unbox,
     Splash,
 }
 
+impl FromStr for LattitudePage {
+    type Err = String;
+
+    /// Case-insensitive variant name, e.g. `splash`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        [LattitudePage::Unbox, LattitudePage::Splash]
+            .into_iter()
+            .find(|page| format!("{:?}", page).eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("no page named {}", name))
+    }
+}
+
 pub fn build_page_manager<const WIDTH: u32, const HEIGHT: u32>(
     font: &FontRegistry<Font>,
     art: &ArtRegistry,
+    models: &ModelManager,
 ) -> PageManager<LattitudePage, WIDTH, HEIGHT> {
     let mut manager = PageManager::new();
 
-    let ctx = PageContext::new(font, art);
+    let ctx = PageContext::new(font, art, models);
 
     manager.register(LattitudePage::Unbox, unbox_page(&ctx));
     manager.register(LattitudePage::Splash, splash_page(&ctx));
 
     manager
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::art::build_art_registry;
+    use crate::font::build_font_registry;
+    use crate::integration::birdnet::RecentDetections;
+    use engine::model::Model;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn splash_lists_birds_of_instances_registered_later() {
+        let font = build_font_registry().unwrap();
+        let art = build_art_registry().unwrap();
+        let mut models = ModelManager::default();
+
+        let splash = splash_page(&PageContext::new(&font, &art, &models));
+        let without = splash.render(&models).await.len();
+
+        let backyard = Model::<RecentDetections>::default();
+        models.register("birdnet.backyard", backyard.clone());
+        assert_eq!(splash.render(&models).await.len(), without);
+
+        let subscription = splash.subscribe(&models);
+        backyard
+            .update(
+                serde_json::from_value(json!({
+                    "detections": [{
+                        "timestamp": "2024-03-01T07:00:00Z",
+                        "species": {
+                            "commonName": "American Robin",
+                            "scientificName": "Turdus migratorius",
+                        },
+                    }],
+                }))
+                .unwrap(),
+            )
+            .await;
+        assert!(subscription.has_changed());
+        assert!(splash.render(&models).await.len() > without);
+    }
+}
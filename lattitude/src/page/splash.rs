@@ -1,7 +1,8 @@
 use crate::art::Art;
 use crate::font::Font;
-use crate::integration::birdnet::BirdList;
+use crate::integration::birdnet::{BirdList, RecentDetections};
 use crate::page::PageContext;
+use engine::model::ModelKey;
 use engine::page;
 use engine::page::Page;
 use engine::view::rotate::Rotate;
@@ -18,21 +19,20 @@ pub fn splash_page(ctx: &PageContext) -> Page {
             Rotate::new(ctx.art(Art::Logo), Rotation::Clockwise(25.0)),
         );
 
-        /*
+        // from whichever instance is preferred when rendering, and nothing
+        // until one has detections
         canvas.place(
             (800, 500),
             HorizontalAlignment::Right,
             VerticalAlignment::Top,
             BirdList::new(
-                birds.clone(),
+                ModelKey::<RecentDetections>::preferred(),
                 300,
                 ctx.font(Font::Typewriter),
                 10.0,
-            )
+            ),
         );
 
-         */
-
         canvas.place(
             (400, 200),
             HorizontalAlignment::Center,